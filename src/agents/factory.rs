@@ -0,0 +1,300 @@
+//! Agent factory for building agents from declarative configuration.
+
+use crate::agents::parallel::CombineStrategy;
+use crate::agents::retry::RetryStrategy;
+use crate::agents::router::RouteRule;
+use crate::agents::summarizer::SummarizerConfig;
+use crate::agents::{ParallelAgent, RetryAgent, RouterAgent, SummarizerAgent, ValidatorAgent};
+use crate::errors::{LLMGraphError, LLMGraphResult};
+use crate::models::graph::Agent;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Type alias for agent builders.
+///
+/// A builder receives the agent's `config` and the factory, so agents that
+/// wrap other agents can build them too.
+pub type AgentBuilder = dyn Fn(&Value, &AgentFactory) -> LLMGraphResult<Box<dyn Agent>> + Send + Sync;
+
+/// An agent by type name and configuration, as written in a graph spec.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSpec {
+    /// The type name registered with the [`AgentFactory`]
+    #[serde(rename = "type")]
+    pub agent_type: String,
+    /// The builder options of the agent type
+    #[serde(default)]
+    pub config: Value,
+}
+
+/// Registry mapping agent type names to builders.
+///
+/// [`AgentFactory::new`] knows the built-in agents:
+///
+/// - `router`: `routes` (list of [`RouteRule`]), `default`, `case_sensitive`, `name`
+/// - `validator`: `rules` (`{"pattern": {"name", "pattern", "message", "critical"}}` or
+///   `{"length": {"min", "max", "critical"}}`), `success_route`, `failure_route`, `strict`, `name`
+/// - `summarizer`: the fields of [`SummarizerConfig`], plus `name`
+/// - `retry`: `agent` (an [`AgentSpec`]), `max_retries`, `strategy` ([`RetryStrategy`]), `verbose`
+/// - `parallel`: `agents` (list of [`AgentSpec`]), `strategy` (`concatenate`, `first_valid`
+///   or `json_array`), `timeout_ms`, `name`
+///
+/// # Example
+/// ```rust
+/// use llmgraph::agents::AgentFactory;
+/// use serde_json::json;
+///
+/// let factory = AgentFactory::new();
+/// let router = factory
+///     .build("router", &json!({
+///         "routes": [{"pattern": "refund|invoice", "target_node": 1}],
+///         "default": 2
+///     }))
+///     .unwrap();
+/// assert_eq!(router.declared_routes(), vec![1, 2]);
+/// ```
+#[derive(Clone)]
+pub struct AgentFactory {
+    builders: HashMap<String, Arc<AgentBuilder>>,
+}
+
+impl AgentFactory {
+    /// Create a factory with the built-in agent types
+    pub fn new() -> Self {
+        let mut factory = Self::empty();
+        factory.register("router", build_router);
+        factory.register("validator", build_validator);
+        factory.register("summarizer", build_summarizer);
+        factory.register("retry", build_retry);
+        factory.register("parallel", build_parallel);
+        factory
+    }
+
+    /// Create a factory without any agent types
+    pub fn empty() -> Self {
+        Self {
+            builders: HashMap::new(),
+        }
+    }
+
+    /// Register a builder for `agent_type`, replacing any existing one
+    pub fn register<F>(&mut self, agent_type: impl Into<String>, builder: F)
+    where
+        F: Fn(&Value, &AgentFactory) -> LLMGraphResult<Box<dyn Agent>> + Send + Sync + 'static,
+    {
+        self.builders.insert(agent_type.into(), Arc::new(builder));
+    }
+
+    /// Whether a builder is registered for `agent_type`
+    pub fn contains(&self, agent_type: &str) -> bool {
+        self.builders.contains_key(agent_type)
+    }
+
+    /// Build an agent of `agent_type` from its configuration
+    pub fn build(&self, agent_type: &str, config: &Value) -> LLMGraphResult<Box<dyn Agent>> {
+        let builder = self.builders.get(agent_type).ok_or_else(|| {
+            LLMGraphError::SpecError(format!("Unknown agent type '{}'", agent_type))
+        })?;
+        builder(config, self)
+    }
+
+    /// Build the agent described by `spec`
+    pub fn build_spec(&self, spec: &AgentSpec) -> LLMGraphResult<Box<dyn Agent>> {
+        self.build(&spec.agent_type, &spec.config)
+    }
+}
+
+impl Default for AgentFactory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Deserialize an agent's config; a missing config counts as empty.
+pub fn parse_config<T: DeserializeOwned>(agent_type: &str, config: &Value) -> LLMGraphResult<T> {
+    let config = match config {
+        Value::Null => Value::Object(Default::default()),
+        config => config.clone(),
+    };
+    serde_json::from_value(config).map_err(|e| {
+        LLMGraphError::SpecError(format!("Invalid config for agent type '{}': {}", agent_type, e))
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RouterConfig {
+    #[serde(default)]
+    routes: Vec<RouteRule>,
+    default: Option<i32>,
+    #[serde(default)]
+    case_sensitive: bool,
+    name: Option<String>,
+}
+
+fn build_router(config: &Value, _: &AgentFactory) -> LLMGraphResult<Box<dyn Agent>> {
+    let config: RouterConfig = parse_config("router", config)?;
+    let mut router = RouterAgent::new().set_case_sensitive(config.case_sensitive);
+    for rule in config.routes {
+        // The router lowercases patterns when matching case-insensitively
+        let pattern = if config.case_sensitive {
+            rule.pattern.clone()
+        } else {
+            rule.pattern.to_lowercase()
+        };
+        regex::Regex::new(&pattern).map_err(|e| {
+            LLMGraphError::SpecError(format!(
+                "Invalid pattern for route to node {}: {}",
+                rule.target_node, e
+            ))
+        })?;
+        router = router.add_route(rule);
+    }
+    if let Some(default) = config.default {
+        router = router.set_default(default);
+    }
+    if let Some(name) = config.name {
+        router = router.with_name(name);
+    }
+    Ok(Box::new(router))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum ValidationRuleConfig {
+    Pattern {
+        name: String,
+        pattern: String,
+        message: String,
+        #[serde(default)]
+        critical: bool,
+    },
+    Length {
+        min: Option<usize>,
+        max: Option<usize>,
+        #[serde(default)]
+        critical: bool,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ValidatorConfig {
+    #[serde(default)]
+    rules: Vec<ValidationRuleConfig>,
+    success_route: Option<i32>,
+    failure_route: Option<i32>,
+    #[serde(default)]
+    strict: bool,
+    name: Option<String>,
+}
+
+fn build_validator(config: &Value, _: &AgentFactory) -> LLMGraphResult<Box<dyn Agent>> {
+    let config: ValidatorConfig = parse_config("validator", config)?;
+    let mut validator = ValidatorAgent::new().with_strict_mode(config.strict);
+    for rule in config.rules {
+        validator = match rule {
+            ValidationRuleConfig::Pattern { name, pattern, message, critical } => {
+                regex::Regex::new(&pattern).map_err(|e| {
+                    LLMGraphError::SpecError(format!("Invalid pattern for rule '{}': {}", name, e))
+                })?;
+                validator.add_pattern_rule(name, pattern, message, critical)
+            }
+            ValidationRuleConfig::Length { min, max, critical } => {
+                validator.add_length_rule(min, max, critical)
+            }
+        };
+    }
+    if let Some(node_id) = config.success_route {
+        validator = validator.with_success_route(node_id);
+    }
+    if let Some(node_id) = config.failure_route {
+        validator = validator.with_failure_route(node_id);
+    }
+    if let Some(name) = config.name {
+        validator = validator.with_name(name);
+    }
+    Ok(Box::new(validator))
+}
+
+#[derive(Deserialize)]
+struct SummarizerSpecConfig {
+    name: Option<String>,
+    #[serde(flatten)]
+    config: SummarizerConfig,
+}
+
+fn build_summarizer(config: &Value, _: &AgentFactory) -> LLMGraphResult<Box<dyn Agent>> {
+    let config: SummarizerSpecConfig = parse_config("summarizer", config)?;
+    let mut summarizer = SummarizerAgent::new().with_config(config.config);
+    if let Some(name) = config.name {
+        summarizer = summarizer.with_name(name);
+    }
+    Ok(Box::new(summarizer))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RetryConfig {
+    agent: AgentSpec,
+    max_retries: Option<usize>,
+    strategy: Option<RetryStrategy>,
+    #[serde(default)]
+    verbose: bool,
+}
+
+fn build_retry(config: &Value, factory: &AgentFactory) -> LLMGraphResult<Box<dyn Agent>> {
+    let config: RetryConfig = parse_config("retry", config)?;
+    let mut retry = RetryAgent::new(factory.build_spec(&config.agent)?).with_verbose(config.verbose);
+    if let Some(max_retries) = config.max_retries {
+        retry = retry.with_max_retries(max_retries);
+    }
+    if let Some(strategy) = config.strategy {
+        retry = retry.with_strategy(strategy);
+    }
+    Ok(Box::new(retry))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CombineStrategyConfig {
+    Concatenate,
+    FirstValid,
+    JsonArray,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ParallelConfig {
+    #[serde(default)]
+    agents: Vec<AgentSpec>,
+    strategy: Option<CombineStrategyConfig>,
+    timeout_ms: Option<u64>,
+    name: Option<String>,
+}
+
+fn build_parallel(config: &Value, factory: &AgentFactory) -> LLMGraphResult<Box<dyn Agent>> {
+    let config: ParallelConfig = parse_config("parallel", config)?;
+    let mut parallel = ParallelAgent::new();
+    for agent in &config.agents {
+        parallel = parallel.add_agent(factory.build_spec(agent)?);
+    }
+    if let Some(strategy) = config.strategy {
+        parallel = parallel.with_strategy(match strategy {
+            CombineStrategyConfig::Concatenate => CombineStrategy::Concatenate,
+            CombineStrategyConfig::FirstValid => CombineStrategy::FirstValid,
+            CombineStrategyConfig::JsonArray => CombineStrategy::JsonArray,
+        });
+    }
+    if let Some(timeout_ms) = config.timeout_ms {
+        parallel = parallel.with_timeout(Duration::from_millis(timeout_ms));
+    }
+    if let Some(name) = config.name {
+        parallel = parallel.with_name(name);
+    }
+    Ok(Box::new(parallel))
+}
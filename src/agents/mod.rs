@@ -6,10 +6,14 @@ pub mod validator;
 pub mod retry;
 pub mod parallel;
 pub mod state;
+pub mod subgraph;
+pub mod factory;
 
 pub use router::RouterAgent;
 pub use summarizer::SummarizerAgent;
 pub use validator::ValidatorAgent;
 pub use retry::RetryAgent;
 pub use parallel::ParallelAgent;
-pub use state::StatefulAgent;
\ No newline at end of file
+pub use state::StatefulAgent;
+pub use subgraph::SubgraphAgent;
+pub use factory::AgentFactory;
\ No newline at end of file
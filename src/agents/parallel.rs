@@ -1,7 +1,10 @@
 //! Parallel agent for executing multiple agents concurrently.
 
 use async_trait::async_trait;
+use crate::errors::{AgentError, ErrorFrame, LLMGraphError, NodeError};
 use crate::models::graph::Agent;
+use crate::models::response::{AgentAction, AgentResponse};
+use crate::models::run::RunContext;
 use crate::models::tools::ToolRegistryTrait;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -19,8 +22,32 @@ pub enum CombineStrategy {
     Custom(Arc<dyn Fn(Vec<String>) -> String + Send + Sync>),
 }
 
+impl CombineStrategy {
+    /// Merge a list of outputs into one
+    pub fn combine(&self, results: Vec<String>) -> String {
+        match self {
+            CombineStrategy::Concatenate => results.join("\n"),
+            CombineStrategy::FirstValid => {
+                results.into_iter()
+                    .find(|s| !s.is_empty())
+                    .unwrap_or_default()
+            }
+            CombineStrategy::JsonArray => {
+                serde_json::json!(results).to_string()
+            }
+            CombineStrategy::Custom(combiner) => combiner(results),
+        }
+    }
+}
+
 /// Parallel agent that runs multiple agents concurrently.
 ///
+/// The agents run outside the graph, so they only see the tools passed to the
+/// parallel agent itself. Agents that fail are left out of the combined output;
+/// if all of them fail, the parallel agent fails with the first error, whose
+/// [`ErrorContext`](crate::errors::ErrorContext) names the failed agent. To fan out across graph nodes instead, see
+/// [`Graph::add_fan_out`](crate::Graph::add_fan_out).
+///
 /// # Example
 /// ```rust,ignore
 /// use llmgraph::agents::ParallelAgent;
@@ -63,7 +90,7 @@ impl ParallelAgent {
         self
     }
 
-    /// Set execution timeout
+    /// Fail with [`NodeError::Timeout`] if the agents take longer than `timeout`
     pub fn with_timeout(mut self, timeout: tokio::time::Duration) -> Self {
         self.timeout = Some(timeout);
         self
@@ -77,18 +104,7 @@ impl ParallelAgent {
 
     /// Combine results based on strategy
     fn combine_results(&self, results: Vec<String>) -> String {
-        match &self.strategy {
-            CombineStrategy::Concatenate => results.join("\n"),
-            CombineStrategy::FirstValid => {
-                results.into_iter()
-                    .find(|s| !s.is_empty())
-                    .unwrap_or_default()
-            }
-            CombineStrategy::JsonArray => {
-                serde_json::json!(results).to_string()
-            }
-            CombineStrategy::Custom(combiner) => combiner(results),
-        }
+        self.strategy.combine(results)
     }
 }
 
@@ -105,8 +121,27 @@ impl Agent for ParallelAgent {
         input: &str,
         tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
     ) -> (String, Option<i32>) {
+        let ctx = RunContext::new(0, Default::default());
+        self.run_with_context(input, tool_registry, &ctx).await
+    }
+
+    async fn run_with_context(
+        &mut self,
+        input: &str,
+        tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ctx: &RunContext,
+    ) -> (String, Option<i32>) {
+        self.execute(input, tool_registry, ctx).await.into()
+    }
+
+    async fn execute(
+        &mut self,
+        input: &str,
+        tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ctx: &RunContext,
+    ) -> AgentResponse {
         if self.agents.is_empty() {
-            return ("No agents configured for parallel execution".to_string(), None);
+            return AgentResponse::end("No agents configured for parallel execution");
         }
 
         // Create futures for all agents
@@ -117,26 +152,47 @@ impl Agent for ParallelAgent {
             
             let future = async move {
                 let mut agent_guard = agent.lock().await;
-                agent_guard.run(&input_clone, tool_registry).await.0
+                let response = agent_guard.execute(&input_clone, tool_registry, ctx).await;
+                (agent_guard.get_name().to_string(), response)
             };
             
             futures.push(future);
         }
 
         // Execute all agents in parallel
-        let results = if let Some(timeout) = self.timeout {
+        let responses = if let Some(timeout) = self.timeout {
             match tokio::time::timeout(timeout, futures::future::join_all(futures)).await {
-                Ok(results) => results,
+                Ok(responses) => responses,
+                Err(_) if ctx.in_node() => {
+                    return AgentResponse::fail(NodeError::Timeout { node_id: ctx.node_id(), timeout });
+                }
                 Err(_) => {
-                    return ("Parallel execution timed out".to_string(), None);
+                    // Outside a graph there is no node to blame
+                    let error = AgentError::ExecutionFailed {
+                        agent: self.name.clone(),
+                        error: format!("timed out after {:?}", timeout),
+                    };
+                    return AgentResponse::fail(LLMGraphError::from(error).within(ErrorFrame::agent(&self.name)));
                 }
             }
         } else {
             futures::future::join_all(futures).await
         };
 
-        let combined = self.combine_results(results);
-        (combined, None)
+        let mut results = Vec::new();
+        let mut first_failure = None;
+        for (name, response) in responses {
+            match response.action {
+                AgentAction::Fail(error) => {
+                    first_failure.get_or_insert(error.within(ErrorFrame::agent(name)));
+                }
+                _ => results.push(response.output),
+            }
+        }
+        match first_failure {
+            Some(error) if results.is_empty() => AgentResponse::fail(error),
+            _ => AgentResponse::end(self.combine_results(results)),
+        }
     }
 
     fn get_name(&self) -> &str {
@@ -1,32 +1,76 @@
 //! Retry agent for handling failures with configurable retry strategies.
 
 use async_trait::async_trait;
+use crate::errors::{ErrorFrame, LLMGraphResult};
 use crate::models::graph::Agent;
+use crate::models::response::{AgentAction, AgentResponse};
+use crate::models::run::RunContext;
 use crate::models::tools::ToolRegistryTrait;
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tokio::time::sleep;
 
-/// Retry strategy configuration
-#[derive(Debug, Clone)]
+/// Retry strategy configuration.
+///
+/// When (de)serialized, variants are snake_case and durations are in
+/// milliseconds, e.g. `{"exponential_backoff": {"base": 100, "max": 10000, "multiplier": 2.0}}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum RetryStrategy {
     /// Fixed delay between retries
-    Fixed(Duration),
+    Fixed(#[serde(with = "millis")] Duration),
     /// Exponential backoff with base delay
     ExponentialBackoff {
+        #[serde(with = "millis")]
         base: Duration,
+        #[serde(with = "millis")]
         max: Duration,
         multiplier: f64,
     },
     /// Linear backoff with increment
     Linear {
+        #[serde(with = "millis")]
         initial: Duration,
+        #[serde(with = "millis")]
         increment: Duration,
+        #[serde(with = "millis")]
         max: Duration,
     },
 }
 
+/// (De)serialize durations as whole milliseconds
+mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// Type alias for custom retry conditions.
+pub type RetryCondition = dyn Fn(&str) -> bool + Send + Sync;
+
 /// Retry agent that wraps another agent and retries on failure.
 ///
+/// An attempt fails when the inner agent returns
+/// [`AgentAction::Fail`](crate::AgentAction::Fail), or when its output matches
+/// the retry condition set with [`RetryAgent::with_retry_condition`].
+/// Without a condition, agents that only implement [`Agent::run`] are also
+/// retried on failures they report as text: empty output or output
+/// containing "error", "failed" or "exception". Agents that override
+/// [`Agent::execute`] report failures as `Fail`, so their output is not
+/// checked for keywords.
+///
+/// Once retries are exhausted, the last attempt's response is returned with
+/// the output "All N retries exhausted. Last output: ...", and a failure's
+/// [`ErrorContext`](crate::errors::ErrorContext) names the inner agent and
+/// the attempt.
+///
 /// # Example
 /// ```rust,ignore
 /// use llmgraph::agents::RetryAgent;
@@ -50,7 +94,7 @@ pub struct RetryAgent {
     inner: Box<dyn Agent>,
     max_retries: usize,
     strategy: RetryStrategy,
-    retry_condition: Option<Box<dyn Fn(&str) -> bool + Send + Sync>>,
+    retry_condition: Option<Box<RetryCondition>>,
     name: String,
     verbose: bool,
 }
@@ -81,7 +125,8 @@ impl RetryAgent {
         self
     }
 
-    /// Set a custom retry condition
+    /// Retry attempts whose output matches `condition`, instead of the
+    /// default error keyword check
     pub fn with_retry_condition<F>(mut self, condition: F) -> Self
     where
         F: Fn(&str) -> bool + Send + Sync + 'static,
@@ -113,13 +158,19 @@ impl RetryAgent {
         }
     }
 
-    /// Check if output indicates a failure that should be retried
-    fn should_retry(&self, output: &str) -> bool {
+    /// Check if a response is a failure that should be retried
+    fn should_retry(&self, response: &AgentResponse) -> bool {
+        if response.is_failure() {
+            return true;
+        }
+        let output = &response.output;
         if let Some(condition) = &self.retry_condition {
             condition(output)
+        } else if !response.from_run {
+            false
         } else {
-            // Default: retry on empty output or error keywords
-            output.is_empty() 
+            // Default for run-only agents: retry on empty output or error keywords
+            output.is_empty()
                 || output.to_lowercase().contains("error")
                 || output.to_lowercase().contains("failed")
                 || output.to_lowercase().contains("exception")
@@ -134,44 +185,77 @@ impl Agent for RetryAgent {
         input: &str,
         tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
     ) -> (String, Option<i32>) {
-        let mut last_output = String::new();
-        let mut last_next_node = None;
-
-        for attempt in 0..=self.max_retries {
-            if attempt > 0 {
-                let delay = self.calculate_delay(attempt - 1);
-                if self.verbose {
-                    println!("Retry attempt {} after {:?} delay", attempt, delay);
-                }
-                sleep(delay).await;
-            }
+        let ctx = RunContext::new(0, Default::default());
+        self.run_with_context(input, tool_registry, &ctx).await
+    }
+
+    async fn run_with_context(
+        &mut self,
+        input: &str,
+        tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ctx: &RunContext,
+    ) -> (String, Option<i32>) {
+        self.execute(input, tool_registry, ctx).await.into()
+    }
 
-            let (output, next_node) = self.inner.run(input, tool_registry).await;
-            
-            if !self.should_retry(&output) || attempt == self.max_retries {
-                if self.verbose && attempt > 0 {
+    async fn execute(
+        &mut self,
+        input: &str,
+        tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ctx: &RunContext,
+    ) -> AgentResponse {
+        let mut attempt = 0;
+        loop {
+            let mut response = self.inner.execute(input, tool_registry, ctx).await;
+            let failed = self.should_retry(&response);
+
+            if !failed || attempt == self.max_retries {
+                if self.verbose && attempt > 0 && !failed {
                     println!("Success after {} retries", attempt);
                 }
-                return (output, next_node);
+                if failed {
+                    response.output = format!(
+                        "All {} retries exhausted. Last output: {}",
+                        self.max_retries, response.output
+                    );
+                }
+                response.action = match response.action {
+                    AgentAction::Fail(error) => {
+                        let frame = ErrorFrame::agent(self.inner.get_name()).with_attempt(attempt + 1);
+                        AgentAction::Fail(error.within(frame))
+                    }
+                    action => action,
+                };
+                return response;
             }
 
             if self.verbose {
+                let (output, _): (String, Option<i32>) = response.into();
                 println!("Attempt {} failed: {}", attempt + 1, output);
             }
 
-            last_output = output;
-            last_next_node = next_node;
+            let delay = self.calculate_delay(attempt);
+            attempt += 1;
+            if self.verbose {
+                println!("Retry attempt {} after {:?} delay", attempt, delay);
+            }
+            sleep(delay).await;
         }
-
-        // Return last attempt's result if all retries exhausted
-        let failure_msg = format!(
-            "All {} retries exhausted. Last output: {}",
-            self.max_retries, last_output
-        );
-        (failure_msg, last_next_node)
     }
 
     fn get_name(&self) -> &str {
         &self.name
     }
+
+    fn declared_routes(&self) -> Vec<i32> {
+        self.inner.declared_routes()
+    }
+
+    fn snapshot(&self) -> Option<String> {
+        self.inner.snapshot()
+    }
+
+    fn restore(&mut self, snapshot: &str) -> LLMGraphResult<()> {
+        self.inner.restore(snapshot)
+    }
 }
\ No newline at end of file
@@ -4,15 +4,17 @@ use async_trait::async_trait;
 use crate::models::graph::Agent;
 use crate::models::tools::ToolRegistryTrait;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 /// Configuration for a routing rule
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RouteRule {
     /// Pattern to match (regex)
     pub pattern: String,
     /// Node ID to route to if pattern matches
     pub target_node: i32,
     /// Optional description of this route
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
 }
 
@@ -134,4 +136,12 @@ impl Agent for RouterAgent {
     fn get_name(&self) -> &str {
         &self.name
     }
+
+    fn declared_routes(&self) -> Vec<i32> {
+        self.routes
+            .iter()
+            .map(|rule| rule.target_node)
+            .chain(self.default_route)
+            .collect()
+    }
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 //! Stateful agent with memory and context persistence.
 
 use async_trait::async_trait;
+use crate::errors::LLMGraphResult;
 use crate::models::graph::Agent;
 use crate::models::tools::ToolRegistryTrait;
 use serde::{Serialize, Deserialize};
@@ -8,7 +9,7 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 /// State that can be persisted between agent executions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AgentState {
     /// Key-value store for arbitrary data
     pub data: HashMap<String, serde_json::Value>,
@@ -20,16 +21,8 @@ pub struct AgentState {
     pub execution_count: usize,
 }
 
-impl Default for AgentState {
-    fn default() -> Self {
-        Self {
-            data: HashMap::new(),
-            history: Vec::new(),
-            context: None,
-            execution_count: 0,
-        }
-    }
-}
+/// Type alias for stateful processing functions.
+pub type StateProcessor = dyn Fn(&str, &mut AgentState) -> (String, Option<i32>) + Send + Sync;
 
 /// A stateful agent that maintains memory between executions.
 /// 
@@ -54,7 +47,7 @@ impl Default for AgentState {
 pub struct StatefulAgent {
     name: String,
     state: Arc<Mutex<AgentState>>,
-    processor: Option<Box<dyn Fn(&str, &mut AgentState) -> (String, Option<i32>) + Send + Sync>>,
+    processor: Option<Box<StateProcessor>>,
     max_history: usize,
 }
 
@@ -72,7 +65,7 @@ impl StatefulAgent {
     /// Set the processing function
     pub fn with_processor(
         mut self,
-        processor: Box<dyn Fn(&str, &mut AgentState) -> (String, Option<i32>) + Send + Sync>,
+        processor: Box<StateProcessor>,
     ) -> Self {
         self.processor = Some(processor);
         self
@@ -163,4 +156,12 @@ impl Agent for StatefulAgent {
     fn get_name(&self) -> &str {
         &self.name
     }
+
+    fn snapshot(&self) -> Option<String> {
+        self.save_state().ok()
+    }
+
+    fn restore(&mut self, snapshot: &str) -> LLMGraphResult<()> {
+        Ok(self.load_state(snapshot)?)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,126 @@
+//! Subgraph agent for nesting a whole graph as a single node.
+
+use async_trait::async_trait;
+use crate::errors::{AgentError, LLMGraphError};
+use crate::models::graph::{Agent, Graph};
+use crate::models::response::{AgentAction, AgentResponse};
+use crate::models::run::{RunContext, RunStatus};
+use crate::models::tools::ToolRegistryTrait;
+use std::sync::Arc;
+
+/// Subgraph agent that runs an inner graph and returns its final output.
+///
+/// The inner run shares the outer run's state and event stream. Its steps are
+/// recorded as [`Step::substeps`](crate::Step::substeps) of the subgraph's step,
+/// and its nodes fall back to the outer node's tools for tools the inner graph
+/// doesn't register. After the inner graph finishes, the outer run continues
+/// with the node set by [`SubgraphAgent::with_next`].
+///
+/// Inner runs are not checkpointed, and interrupts inside a subgraph fail the
+/// subgraph instead of pausing the outer run. A failed inner run fails the
+/// subgraph node with the inner error.
+///
+/// # Example
+/// ```rust,ignore
+/// use llmgraph::Graph;
+/// use llmgraph::agents::SubgraphAgent;
+/// use std::sync::Arc;
+///
+/// // Assuming research, draft and review agents are defined
+/// let mut writing = Graph::new();
+/// writing.add_node(0, research);
+/// writing.add_node(1, draft);
+/// writing.add_node(2, review);
+///
+/// let mut graph = Graph::new();
+/// graph.add_node(0, Box::new(SubgraphAgent::new("Writing", Arc::new(writing), 0).with_next(1)));
+/// ```
+pub struct SubgraphAgent {
+    graph: Arc<Graph>,
+    start_id: i32,
+    next_node: Option<i32>,
+    name: String,
+}
+
+impl SubgraphAgent {
+    /// Create a subgraph agent running `graph` from `start_id`
+    pub fn new(name: impl Into<String>, graph: Arc<Graph>, start_id: i32) -> Self {
+        Self {
+            graph,
+            start_id,
+            next_node: None,
+            name: name.into(),
+        }
+    }
+
+    /// Set the outer node to continue with once the inner graph finishes
+    pub fn with_next(mut self, next_node: i32) -> Self {
+        self.next_node = Some(next_node);
+        self
+    }
+}
+
+#[async_trait]
+impl Agent for SubgraphAgent {
+    async fn run(
+        &mut self,
+        input: &str,
+        tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+    ) -> (String, Option<i32>) {
+        let ctx = RunContext::new(0, Default::default());
+        self.run_with_context(input, tool_registry, &ctx).await
+    }
+
+    async fn run_with_context(
+        &mut self,
+        input: &str,
+        tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ctx: &RunContext,
+    ) -> (String, Option<i32>) {
+        self.execute(input, tool_registry, ctx).await.into()
+    }
+
+    async fn execute(
+        &mut self,
+        input: &str,
+        tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ctx: &RunContext,
+    ) -> AgentResponse {
+        let result = self
+            .graph
+            .run_nested(self.start_id, input, tool_registry, ctx)
+            .await;
+        ctx.record_substeps(result.steps);
+
+        let error = match result.status {
+            RunStatus::Paused { node, .. } => Some(
+                AgentError::ExecutionFailed {
+                    agent: self.name.clone(),
+                    error: format!("node {} requested an interrupt, which subgraphs don't support", node),
+                }
+                .into(),
+            ),
+            // Keep the path to the failing inner node
+            _ => result.error.map(|error| match result.error_context {
+                Some(context) => context.path.into_iter().rev().fold(error, LLMGraphError::within),
+                None => error,
+            }),
+        };
+        if let Some(error) = error {
+            let output = format!("Subgraph '{}' failed: {}", self.name, error);
+            return AgentResponse::new(output, AgentAction::Fail(error));
+        }
+        match self.next_node {
+            Some(next) => AgentResponse::next(result.output, next),
+            None => AgentResponse::end(result.output),
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn declared_routes(&self) -> Vec<i32> {
+        self.next_node.into_iter().collect()
+    }
+}
@@ -3,9 +3,13 @@
 use async_trait::async_trait;
 use crate::models::graph::Agent;
 use crate::models::tools::ToolRegistryTrait;
+use serde::{Deserialize, Serialize};
 
-/// Configuration for summarization
-#[derive(Debug, Clone)]
+/// Configuration for summarization.
+///
+/// Missing fields take their default values when deserialized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SummarizerConfig {
     /// Maximum length of summary in characters
     pub max_length: usize,
@@ -17,7 +21,9 @@ pub struct SummarizerConfig {
     pub style: SummaryStyle,
 }
 
-#[derive(Debug, Clone)]
+/// Summary style, written in snake_case in specs (e.g. `key_points`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SummaryStyle {
     /// Bullet points
     Bullets,
@@ -5,13 +5,16 @@ use crate::models::graph::Agent;
 use crate::models::tools::ToolRegistryTrait;
 use regex::Regex;
 
+/// Type alias for validation functions.
+pub type ValidatorFunction = dyn Fn(&str) -> Result<(), String> + Send + Sync;
+
 /// Validation rule for checking inputs/outputs
 #[derive(Clone)]
 pub struct ValidationRule {
     /// Name of the rule
     pub name: String,
     /// Validation function
-    pub validator: Arc<dyn Fn(&str) -> Result<(), String> + Send + Sync>,
+    pub validator: Arc<ValidatorFunction>,
     /// Whether this rule is critical (stops execution if failed)
     pub critical: bool,
 }
@@ -211,4 +214,11 @@ impl Agent for ValidatorAgent {
     fn get_name(&self) -> &str {
         &self.name
     }
+
+    fn declared_routes(&self) -> Vec<i32> {
+        self.next_node_on_success
+            .into_iter()
+            .chain(self.next_node_on_failure)
+            .collect()
+    }
 }
\ No newline at end of file
@@ -4,6 +4,7 @@
 
 use std::fmt;
 use std::error::Error;
+use std::time::Duration;
 
 /// Main error type for the LLMGraph library
 #[derive(Debug)]
@@ -20,6 +21,114 @@ pub enum LLMGraphError {
     GraphError(GraphError),
     /// Serialization/Deserialization errors
     SerializationError(String),
+    /// Checkpoint storage errors
+    CheckpointError(String),
+    /// Invalid declarative graph specs
+    SpecError(String),
+    /// An error raised by an agent run by another agent, with the path to it
+    WithContext {
+        context: ErrorContext,
+        error: Box<LLMGraphError>,
+    },
+}
+
+/// Where in a run an error happened.
+///
+/// Displayed as the run ID followed by the path to the failing agent, e.g.
+/// `run 42: node 2 'Writing' > node 1 'Retry[Draft]' > 'Draft' (attempt 3)`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorContext {
+    /// The ID of the run
+    pub run_id: Option<String>,
+    /// The nodes and wrapping agents the error passed through, outermost first
+    pub path: Vec<ErrorFrame>,
+}
+
+impl ErrorContext {
+    /// The innermost graph node on the path
+    pub fn node_id(&self) -> Option<i32> {
+        self.path.iter().rev().find_map(|frame| frame.node_id)
+    }
+
+    /// The innermost agent on the path
+    pub fn agent(&self) -> Option<&str> {
+        self.path.last().map(|frame| frame.agent.as_str())
+    }
+
+    /// The attempt of the innermost retried agent on the path
+    pub fn attempt(&self) -> Option<usize> {
+        self.path.iter().rev().find_map(|frame| frame.attempt)
+    }
+}
+
+/// One level of an [`ErrorContext`] path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorFrame {
+    /// The graph node, or `None` for an agent run by another agent
+    pub node_id: Option<i32>,
+    /// The agent's name
+    pub agent: String,
+    /// The failed attempt, for retried agents
+    pub attempt: Option<usize>,
+}
+
+impl ErrorFrame {
+    /// A frame for the agent of a graph node
+    pub fn node(node_id: i32, agent: impl Into<String>) -> Self {
+        Self {
+            node_id: Some(node_id),
+            agent: agent.into(),
+            attempt: None,
+        }
+    }
+
+    /// A frame for an agent run by another agent, e.g. inside a `ParallelAgent`
+    pub fn agent(agent: impl Into<String>) -> Self {
+        Self {
+            node_id: None,
+            agent: agent.into(),
+            attempt: None,
+        }
+    }
+
+    /// Record which attempt failed
+    pub fn with_attempt(mut self, attempt: usize) -> Self {
+        self.attempt = Some(attempt);
+        self
+    }
+}
+
+impl LLMGraphError {
+    /// The error without its context
+    pub fn root(&self) -> &LLMGraphError {
+        match self {
+            LLMGraphError::WithContext { error, .. } => error.root(),
+            error => error,
+        }
+    }
+
+    /// The path to the agent that raised the error, if it has one
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            LLMGraphError::WithContext { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Add an outer frame to the error's path
+    pub fn within(self, frame: ErrorFrame) -> Self {
+        let (mut context, error) = self.into_parts();
+        context.path.insert(0, frame);
+        LLMGraphError::WithContext { context, error }
+    }
+
+    /// Split the error into its context and the error itself
+    pub fn into_parts(self) -> (ErrorContext, Box<LLMGraphError>) {
+        match self {
+            LLMGraphError::WithContext { context, error } => (context, error),
+            error => (ErrorContext::default(), Box::new(error)),
+        }
+    }
 }
 
 /// Errors related to node operations
@@ -28,6 +137,8 @@ pub enum NodeError {
     NodeNotFound(i32),
     NodeAlreadyExists(i32),
     InvalidNodeId(i32),
+    /// The node's agent exceeded its timeout (see `Graph::set_node_timeout`)
+    Timeout { node_id: i32, timeout: Duration },
 }
 
 /// Errors related to tool operations
@@ -47,6 +158,26 @@ pub enum ApiError {
     RateLimitExceeded,
     AuthenticationFailed,
     Timeout,
+    /// The HTTP request couldn't be sent or its response couldn't be read
+    Http(reqwest::Error),
+    /// The API answered with an unsuccessful status, with the response body
+    Status { status: u16, body: String },
+}
+
+impl ApiError {
+    /// Map an unsuccessful HTTP status and its response body to an error.
+    ///
+    /// 401 and 403 become [`ApiError::AuthenticationFailed`], 429 becomes
+    /// [`ApiError::RateLimitExceeded`], 408 and 504 become [`ApiError::Timeout`]
+    /// and anything else becomes [`ApiError::Status`].
+    pub fn from_status(status: u16, body: impl Into<String>) -> Self {
+        match status {
+            401 | 403 => ApiError::AuthenticationFailed,
+            429 => ApiError::RateLimitExceeded,
+            408 | 504 => ApiError::Timeout,
+            _ => ApiError::Status { status, body: body.into() },
+        }
+    }
 }
 
 /// Errors related to agent execution
@@ -61,10 +192,20 @@ pub enum AgentError {
 /// Errors related to graph structure
 #[derive(Debug)]
 pub enum GraphError {
-    CycleDetected,
-    DisconnectedGraph,
+    /// A cycle through the given node path
+    CycleDetected(Vec<i32>),
+    /// Nodes that can't be reached from the start node
+    DisconnectedGraph(Vec<i32>),
     InvalidEdge { from: i32, to: i32 },
     EmptyGraph,
+    /// The run's cancellation token was cancelled
+    Cancelled,
+    /// The run's deadline passed
+    DeadlineExceeded,
+    /// Middleware stopped the run, with the reason
+    Stopped(String),
+    /// A fan-out node without branches
+    EmptyFanOut(i32),
 }
 
 impl fmt::Display for LLMGraphError {
@@ -76,7 +217,41 @@ impl fmt::Display for LLMGraphError {
             LLMGraphError::AgentError(e) => write!(f, "Agent error: {}", e),
             LLMGraphError::GraphError(e) => write!(f, "Graph error: {}", e),
             LLMGraphError::SerializationError(e) => write!(f, "Serialization error: {}", e),
+            LLMGraphError::CheckpointError(e) => write!(f, "Checkpoint error: {}", e),
+            LLMGraphError::SpecError(e) => write!(f, "Invalid graph spec: {}", e),
+            LLMGraphError::WithContext { context, error } => write!(f, "{} (at {})", error, context),
+        }
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(run_id) = &self.run_id {
+            write!(f, "run {}", run_id)?;
+            if !self.path.is_empty() {
+                write!(f, ": ")?;
+            }
         }
+        for (i, frame) in self.path.iter().enumerate() {
+            if i > 0 {
+                write!(f, " > ")?;
+            }
+            write!(f, "{}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ErrorFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(node_id) = self.node_id {
+            write!(f, "node {} ", node_id)?;
+        }
+        write!(f, "'{}'", self.agent)?;
+        if let Some(attempt) = self.attempt {
+            write!(f, " (attempt {})", attempt)?;
+        }
+        Ok(())
     }
 }
 
@@ -86,6 +261,9 @@ impl fmt::Display for NodeError {
             NodeError::NodeNotFound(id) => write!(f, "Node {} not found", id),
             NodeError::NodeAlreadyExists(id) => write!(f, "Node {} already exists", id),
             NodeError::InvalidNodeId(id) => write!(f, "Invalid node ID: {}", id),
+            NodeError::Timeout { node_id, timeout } => {
+                write!(f, "Node {} timed out after {:?}", node_id, timeout)
+            }
         }
     }
 }
@@ -115,6 +293,10 @@ impl fmt::Display for ApiError {
             ApiError::RateLimitExceeded => write!(f, "API rate limit exceeded"),
             ApiError::AuthenticationFailed => write!(f, "API authentication failed"),
             ApiError::Timeout => write!(f, "API request timed out"),
+            ApiError::Http(e) => write!(f, "HTTP request failed: {}", e),
+            ApiError::Status { status, body } => {
+                write!(f, "API request failed with status {}: {}", status, body)
+            }
         }
     }
 }
@@ -135,20 +317,53 @@ impl fmt::Display for AgentError {
 impl fmt::Display for GraphError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            GraphError::CycleDetected => write!(f, "Cycle detected in graph"),
-            GraphError::DisconnectedGraph => write!(f, "Graph is disconnected"),
+            GraphError::CycleDetected(path) => {
+                let path: Vec<String> = path.iter().map(|id| id.to_string()).collect();
+                write!(f, "Cycle detected in graph: {}", path.join(" -> "))
+            }
+            GraphError::DisconnectedGraph(unreachable) => {
+                write!(f, "Graph is disconnected; unreachable nodes: {:?}", unreachable)
+            }
             GraphError::InvalidEdge { from, to } => {
                 write!(f, "Invalid edge from {} to {}", from, to)
             }
             GraphError::EmptyGraph => write!(f, "Graph is empty"),
+            GraphError::Cancelled => write!(f, "Run was cancelled"),
+            GraphError::DeadlineExceeded => write!(f, "Run deadline exceeded"),
+            GraphError::Stopped(reason) => write!(f, "Run stopped: {}", reason),
+            GraphError::EmptyFanOut(id) => write!(f, "Fan-out {} has no branches", id),
+        }
+    }
+}
+
+impl Error for LLMGraphError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LLMGraphError::NodeError(e) => Some(e),
+            LLMGraphError::ToolError(e) => Some(e),
+            LLMGraphError::ApiError(e) => Some(e),
+            LLMGraphError::AgentError(e) => Some(e),
+            LLMGraphError::GraphError(e) => Some(e),
+            LLMGraphError::WithContext { error, .. } => Some(error.as_ref()),
+            LLMGraphError::SerializationError(_)
+            | LLMGraphError::CheckpointError(_)
+            | LLMGraphError::SpecError(_) => None,
         }
     }
 }
 
-impl Error for LLMGraphError {}
 impl Error for NodeError {}
 impl Error for ToolError {}
-impl Error for ApiError {}
+
+impl Error for ApiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ApiError::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl Error for AgentError {}
 impl Error for GraphError {}
 
@@ -186,6 +401,27 @@ impl From<GraphError> for LLMGraphError {
     }
 }
 
+/// Timeouts become [`ApiError::Timeout`]; anything else, including errors
+/// from `error_for_status`, is kept as [`ApiError::Http`], as the response
+/// body is no longer available to build an [`ApiError::Status`]. The request
+/// URL is dropped, as it may hold an API key.
+impl From<reqwest::Error> for ApiError {
+    fn from(error: reqwest::Error) -> Self {
+        let error = error.without_url();
+        if error.is_timeout() {
+            ApiError::Timeout
+        } else {
+            ApiError::Http(error)
+        }
+    }
+}
+
+impl From<reqwest::Error> for LLMGraphError {
+    fn from(error: reqwest::Error) -> Self {
+        LLMGraphError::ApiError(error.into())
+    }
+}
+
 impl From<serde_json::Error> for LLMGraphError {
     fn from(error: serde_json::Error) -> Self {
         LLMGraphError::SerializationError(error.to_string())
@@ -0,0 +1,357 @@
+//! Client for Anthropic's Messages API.
+
+use async_trait::async_trait;
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+use crate::errors::LLMGraphResult;
+use crate::generate::client::{
+    arguments_value, json_body, merge_turns, parameters_schema, send_json, ChatRequest, ChatResponse, HttpConfig, LlmClient,
+    PendingToolCalls, ResponseFormat, ToolChoice, Usage,
+};
+use crate::models::tools::{Choice, FunctionCall, Message, Tool, ToolCall};
+
+/// The API version sent in the `anthropic-version` header
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// The request body of a Messages API call
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct AnthropicMessage {
+    role: &'static str,
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Serialize)]
+struct AnthropicTool<'a> {
+    name: &'a str,
+    description: &'a str,
+    input_schema: Value,
+}
+
+/// A content block of a message
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text { text: String },
+    ToolUse { id: String, name: String, input: Value },
+    ToolResult { tool_use_id: String, content: String },
+    /// Blocks llmgraph has no representation for, such as thinking
+    #[serde(other)]
+    Other,
+}
+
+/// The response body of a Messages API call
+#[derive(Deserialize)]
+struct MessagesResponse {
+    model: Option<String>,
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+}
+
+/// An [`LlmClient`] for Anthropic's `/v1/messages` endpoint.
+///
+/// Requests and responses are translated from and to llmgraph's OpenAI-style
+/// messages, so agents can switch providers without changing their tool loop:
+///
+/// - `system` messages are joined into the request's `system` field
+/// - assistant tool calls become `tool_use` blocks, and `tool` messages become
+///   `tool_result` blocks answering the preceding tool calls in order
+/// - `stop_reason` maps onto `finish_reason` (`end_turn` and `stop_sequence`
+///   become `stop`, `max_tokens` becomes `length`, `tool_use` becomes
+///   `tool_calls`)
+/// - `tool_choice` and `parallel_tool_calls` become Anthropic's `tool_choice`,
+///   and `user` becomes `metadata.user_id`; `seed` and the penalties are not
+///   supported and are ignored
+/// - a JSON schema `response_format` becomes a tool named after the schema,
+///   with the schema as its input, that the model is forced to call; its
+///   input is returned as the response text. Other response formats are
+///   ignored
+///
+/// Streaming uses the default [`LlmClient::chat_stream`], replaying the
+/// complete response.
+///
+/// # Example
+/// ```rust,no_run
+/// use llmgraph::generate::anthropic::AnthropicClient;
+/// use llmgraph::generate::client::{ChatRequest, LlmClient};
+/// use llmgraph::Message;
+///
+/// # async fn example() -> llmgraph::LLMGraphResult<()> {
+/// let client = AnthropicClient::new("sk-ant-...")
+///     .with_model("claude-sonnet-4-5")
+///     .with_max_tokens(1024);
+///
+/// let request = ChatRequest::new(vec![Message {
+///     role: "user".to_string(),
+///     content: Some("Hello!".to_string()),
+///     tool_calls: None,
+/// }]);
+/// let response = client.chat(request).await?;
+/// println!("{}", response.content().unwrap_or_default());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnthropicClient {
+    http: HttpConfig,
+    base_url: String,
+    api_key: String,
+    model: Option<String>,
+    max_tokens: u32,
+}
+
+impl AnthropicClient {
+    /// Create a client for the Anthropic API, authenticating with `api_key`
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            http: HttpConfig::default(),
+            base_url: "https://api.anthropic.com".to_string(),
+            api_key: api_key.into(),
+            model: None,
+            max_tokens: 4096,
+        }
+    }
+
+    /// Send requests to another host, e.g. a proxy or a stub server
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Set the model used for requests that don't name one
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Set the maximum number of tokens to generate for requests that don't
+    /// set one (4096 by default; the Messages API requires a limit)
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Fail message requests that take longer than `timeout` with
+    /// [`ApiError::Timeout`](crate::errors::ApiError::Timeout)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http.timeout = Some(timeout);
+        self
+    }
+
+    /// Send requests with `http` instead of the client shared by all providers
+    pub fn with_http_client(mut self, http: Client) -> Self {
+        self.http.client = http;
+        self
+    }
+
+    /// The URL message requests are sent to
+    pub fn messages_url(&self) -> String {
+        let base_url = self.base_url.trim_end_matches('/');
+        if base_url.ends_with("/v1/messages") {
+            base_url.to_string()
+        } else {
+            format!("{}/v1/messages", base_url)
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for AnthropicClient {
+    async fn chat(&self, request: ChatRequest) -> LLMGraphResult<ChatResponse> {
+        let model = request.model_or(&self.model)?;
+        let (system, messages) = to_anthropic_messages(&request.messages)?;
+        let mut tools: Vec<AnthropicTool> = request.tools.iter().flatten().map(to_anthropic_tool).collect();
+        let mut tool_choice = to_anthropic_tool_choice(request.tool_choice.as_ref(), request.parallel_tool_calls);
+        // The Messages API has no response format: force a tool call whose
+        // input is the answer instead
+        let answer_tool = match &request.response_format {
+            Some(ResponseFormat::JsonSchema { name, schema, .. }) => {
+                tools.push(AnthropicTool {
+                    name,
+                    description: "Respond with JSON matching the input schema",
+                    input_schema: schema.clone(),
+                });
+                tool_choice = Some(json!({ "type": "tool", "name": name }));
+                Some(name.as_str())
+            }
+            _ => None,
+        };
+        let body = MessagesRequest {
+            model,
+            max_tokens: request.max_tokens.unwrap_or(self.max_tokens),
+            system,
+            messages,
+            temperature: request.temperature,
+            tools,
+            top_p: request.top_p,
+            stop_sequences: request.stop.as_deref(),
+            tool_choice,
+            metadata: request.user.as_ref().map(|user| json!({ "user_id": user })),
+        };
+
+        let http_request = self
+            .http
+            .request(Method::POST, self.messages_url())
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&json_body(&body, &request.extra)?);
+        let response: MessagesResponse = send_json(http_request).await?;
+        Ok(from_anthropic_response(response, answer_tool))
+    }
+}
+
+fn to_anthropic_tool(tool: &Tool) -> AnthropicTool<'_> {
+    AnthropicTool {
+        name: &tool.function.name,
+        description: &tool.function.description,
+        input_schema: parameters_schema(&tool.function.parameters),
+    }
+}
+
+/// Anthropic's `tool_choice`, which also disables parallel tool calls
+fn to_anthropic_tool_choice(tool_choice: Option<&ToolChoice>, parallel_tool_calls: Option<bool>) -> Option<Value> {
+    let mut choice = match tool_choice {
+        None if parallel_tool_calls == Some(false) => json!({ "type": "auto" }),
+        None => return None,
+        Some(ToolChoice::Auto) => json!({ "type": "auto" }),
+        Some(ToolChoice::None) => json!({ "type": "none" }),
+        Some(ToolChoice::Required) => json!({ "type": "any" }),
+        Some(ToolChoice::Function(name)) => json!({ "type": "tool", "name": name }),
+    };
+    if parallel_tool_calls == Some(false) && tool_choice != Some(&ToolChoice::None) {
+        choice["disable_parallel_tool_use"] = Value::Bool(true);
+    }
+    Some(choice)
+}
+
+/// Split off the system prompt and convert the conversation to content blocks
+fn to_anthropic_messages(messages: &[Message]) -> LLMGraphResult<(Option<String>, Vec<AnthropicMessage>)> {
+    let mut system = Vec::new();
+    let mut turns = Vec::with_capacity(messages.len());
+    let mut pending = PendingToolCalls::default();
+
+    for message in messages {
+        let text = message.content.clone().filter(|text| !text.is_empty());
+        let turn = match message.role.as_str() {
+            "system" => {
+                system.extend(text);
+                continue;
+            }
+            "assistant" => {
+                let mut blocks: Vec<ContentBlock> = text.map(|text| ContentBlock::Text { text }).into_iter().collect();
+                pending.expect(message);
+                for call in message.tool_calls.iter().flatten() {
+                    blocks.push(ContentBlock::ToolUse {
+                        id: call.id.clone(),
+                        name: call.function.name.clone(),
+                        input: arguments_value(&call.function.arguments)?,
+                    });
+                }
+                ("assistant", blocks)
+            }
+            "tool" => {
+                let tool_use_id = pending.answer()?.id.clone();
+                let content = message.content.clone().unwrap_or_default();
+                ("user", vec![ContentBlock::ToolResult { tool_use_id, content }])
+            }
+            _ => ("user", text.map(|text| ContentBlock::Text { text }).into_iter().collect()),
+        };
+        turns.push(turn);
+    }
+
+    let system = if system.is_empty() { None } else { Some(system.join("\n\n")) };
+    let converted = merge_turns(turns)
+        .into_iter()
+        .map(|(role, content)| AnthropicMessage { role, content })
+        .collect();
+    Ok((system, converted))
+}
+
+/// Convert a Messages API response to a single-choice chat response, with
+/// the input of a call to `answer_tool` as its text
+fn from_anthropic_response(response: MessagesResponse, answer_tool: Option<&str>) -> ChatResponse {
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    let mut answered = false;
+    for block in response.content {
+        match block {
+            ContentBlock::Text { text: part } => text.push_str(&part),
+            ContentBlock::ToolUse { name, input, .. } if Some(name.as_str()) == answer_tool => {
+                // Replace any preamble with the answer
+                text = input.to_string();
+                answered = true;
+            }
+            ContentBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                id,
+                call_type: "function".to_string(),
+                function: FunctionCall {
+                    name,
+                    arguments: input.to_string(),
+                },
+                thought_signature: None,
+            }),
+            ContentBlock::ToolResult { .. } | ContentBlock::Other => {}
+        }
+    }
+
+    let content = if text.is_empty() && !tool_calls.is_empty() { None } else { Some(text) };
+    let tool_calls = if tool_calls.is_empty() { None } else { Some(tool_calls) };
+    let finish_reason = response.stop_reason.map(|reason| {
+        match reason.as_str() {
+            "tool_use" if answered && tool_calls.is_none() => "stop",
+            "end_turn" | "stop_sequence" => "stop",
+            "max_tokens" => "length",
+            "tool_use" => "tool_calls",
+            other => other,
+        }
+        .to_string()
+    });
+    ChatResponse {
+        choices: vec![Choice {
+            message: Message {
+                role: "assistant".to_string(),
+                content,
+                tool_calls,
+            },
+            finish_reason,
+        }],
+        model: response.model,
+        usage: response.usage.map(|usage| Usage {
+            prompt_tokens: usage.input_tokens,
+            completion_tokens: usage.output_tokens,
+            total_tokens: usage.input_tokens + usage.output_tokens,
+        }),
+    }
+}
@@ -0,0 +1,576 @@
+//! Provider-independent chat model interface.
+//!
+//! An [`LlmClient`] sends a [`ChatRequest`] to a chat model and returns its
+//! [`ChatResponse`], either complete or as a [`ChatStream`] of deltas.
+//! Agents that take an `Arc<dyn LlmClient>` can switch providers, share one
+//! connection pool, or be tested against a mock client.
+
+use async_trait::async_trait;
+use futures::stream::{self, Stream};
+use reqwest::{Client, Method};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::time::Duration;
+use crate::errors::{ApiError, LLMGraphResult};
+use serde_json::{Map, Value};
+use crate::models::tools::{Choice, FunctionCall, LLMResponse, Message, Parameters, Tool, ToolCall};
+
+/// A chat model provider.
+///
+/// # Example
+/// ```rust
+/// use async_trait::async_trait;
+/// use llmgraph::errors::LLMGraphResult;
+/// use llmgraph::generate::client::{ChatRequest, ChatResponse, LlmClient};
+///
+/// /// A client that always answers the same, e.g. for tests
+/// struct CannedClient;
+///
+/// #[async_trait]
+/// impl LlmClient for CannedClient {
+///     async fn chat(&self, _request: ChatRequest) -> LLMGraphResult<ChatResponse> {
+///         Ok(ChatResponse::from_text("Hello!"))
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait LlmClient: Send + Sync {
+    /// Send a chat request and wait for the complete response.
+    async fn chat(&self, request: ChatRequest) -> LLMGraphResult<ChatResponse>;
+
+    /// Send a chat request and stream the response as it is generated.
+    ///
+    /// The stream ends with [`ChatEvent::Done`] holding the response
+    /// assembled from all deltas. The default implementation waits for
+    /// [`LlmClient::chat`] and replays its response as a single set of deltas.
+    async fn chat_stream(&self, request: ChatRequest) -> LLMGraphResult<ChatStream> {
+        let response = self.chat(request).await?;
+        let mut events: Vec<LLMGraphResult<ChatEvent>> = Vec::new();
+        if let Some(content) = response.content().filter(|content| !content.is_empty()) {
+            events.push(Ok(ChatEvent::ContentDelta(content.to_string())));
+        }
+        for (index, call) in response.tool_calls().iter().enumerate() {
+            events.push(Ok(ChatEvent::ToolCallDelta {
+                index,
+                id: Some(call.id.clone()),
+                name: Some(call.function.name.clone()),
+                arguments: call.function.arguments.clone(),
+            }));
+        }
+        events.push(Ok(ChatEvent::Done(response)));
+        Ok(Box::pin(stream::iter(events)))
+    }
+}
+
+/// A streamed chat response.
+pub type ChatStream = Pin<Box<dyn Stream<Item = LLMGraphResult<ChatEvent>> + Send>>;
+
+/// An event of a [`ChatStream`].
+#[derive(Debug, Clone)]
+pub enum ChatEvent {
+    /// The next piece of the assistant's text
+    ContentDelta(String),
+    /// The next fragment of the tool call at `index`; `id` and `name` come
+    /// with the call's first fragment, `arguments` are appended in order
+    ToolCallDelta {
+        index: usize,
+        id: Option<String>,
+        name: Option<String>,
+        arguments: String,
+    },
+    /// The stream is complete; always the last event
+    Done(ChatResponse),
+}
+
+/// A chat request, independent of the provider.
+///
+/// Unset fields fall back to the client's or provider's defaults and are
+/// omitted from the request body. Providers ignore parameters they don't
+/// support.
+#[derive(Debug, Clone, Default)]
+pub struct ChatRequest {
+    /// The model to use, instead of the client's default model
+    pub model: Option<String>,
+    /// The conversation history
+    pub messages: Vec<Message>,
+    /// Sampling temperature
+    pub temperature: Option<f32>,
+    /// Tools the model may call
+    pub tools: Option<Vec<Tool>>,
+    /// The maximum number of tokens to generate
+    pub max_tokens: Option<u32>,
+    /// Nucleus sampling probability mass
+    pub top_p: Option<f32>,
+    /// Sequences that stop generation
+    pub stop: Option<Vec<String>>,
+    /// Seed for deterministic sampling
+    pub seed: Option<i64>,
+    /// Penalty for tokens that already appeared
+    pub presence_penalty: Option<f32>,
+    /// Penalty for tokens by how often they appeared
+    pub frequency_penalty: Option<f32>,
+    /// Whether and which tool the model must call
+    pub tool_choice: Option<ToolChoice>,
+    /// Whether the model may call several tools at once
+    pub parallel_tool_calls: Option<bool>,
+    /// An ID of the end user, for abuse monitoring
+    pub user: Option<String>,
+    /// The format the response must have, such as JSON matching a schema
+    pub response_format: Option<ResponseFormat>,
+    /// Provider-specific fields added to the top level of the request body,
+    /// such as OpenRouter's `provider` routing object. A field with the name
+    /// of one the client sends, such as `max_tokens`, replaces it
+    pub extra: Map<String, Value>,
+}
+
+impl ChatRequest {
+    /// Create a request for the given conversation
+    pub fn new(messages: Vec<Message>) -> Self {
+        Self {
+            messages,
+            ..Self::default()
+        }
+    }
+
+    /// Set the model
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// The request's model, or `default` (a client's model) if it names none
+    pub fn model_or<'a>(&'a self, default: &'a Option<String>) -> LLMGraphResult<&'a str> {
+        self.model
+            .as_deref()
+            .or(default.as_deref())
+            .ok_or_else(|| ApiError::RequestFailed("No model set on the request or the client".to_string()).into())
+    }
+
+    /// Set the sampling temperature
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Set the tools the model may call
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    /// Set the maximum number of tokens to generate
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Set the nucleus sampling probability mass
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    /// Set the sequences that stop generation
+    pub fn with_stop<S: Into<String>>(mut self, stop: impl IntoIterator<Item = S>) -> Self {
+        self.stop = Some(stop.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set the sampling seed
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the presence penalty
+    pub fn with_presence_penalty(mut self, presence_penalty: f32) -> Self {
+        self.presence_penalty = Some(presence_penalty);
+        self
+    }
+
+    /// Set the frequency penalty
+    pub fn with_frequency_penalty(mut self, frequency_penalty: f32) -> Self {
+        self.frequency_penalty = Some(frequency_penalty);
+        self
+    }
+
+    /// Set whether and which tool the model must call
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// Set whether the model may call several tools at once
+    pub fn with_parallel_tool_calls(mut self, parallel_tool_calls: bool) -> Self {
+        self.parallel_tool_calls = Some(parallel_tool_calls);
+        self
+    }
+
+    /// Set the ID of the end user
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Set the format the response must have
+    pub fn with_response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+
+    /// Add a provider-specific field to the request body, replacing any
+    /// field of the same name
+    pub fn with_extra(mut self, key: impl Into<String>, value: Value) -> Self {
+        self.extra.insert(key.into(), value);
+        self
+    }
+}
+
+/// Whether and which tool the model must call.
+///
+/// Serializes in the OpenAI format: `"auto"`, `"none"`, `"required"` or
+/// `{"type": "function", "function": {"name": ...}}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolChoice {
+    /// The model decides whether to call tools
+    Auto,
+    /// The model must not call tools
+    None,
+    /// The model must call at least one tool
+    Required,
+    /// The model must call the named function
+    Function(String),
+}
+
+impl Serialize for ToolChoice {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ToolChoice::Auto => serializer.serialize_str("auto"),
+            ToolChoice::None => serializer.serialize_str("none"),
+            ToolChoice::Required => serializer.serialize_str("required"),
+            ToolChoice::Function(name) => serde_json::json!({
+                "type": "function",
+                "function": { "name": name },
+            })
+            .serialize(serializer),
+        }
+    }
+}
+
+/// The format a response must have.
+///
+/// Serializes in the OpenAI format, e.g. `{"type": "json_schema",
+/// "json_schema": {"name": ..., "schema": ..., "strict": true}}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseFormat {
+    /// Free text
+    Text,
+    /// Any JSON object
+    JsonObject,
+    /// JSON matching `schema`; `name` identifies the schema to the provider
+    JsonSchema { name: String, schema: Value, strict: bool },
+}
+
+impl ResponseFormat {
+    /// JSON strictly matching `schema`
+    pub fn json_schema(name: impl Into<String>, schema: Value) -> Self {
+        ResponseFormat::JsonSchema {
+            name: name.into(),
+            schema,
+            strict: true,
+        }
+    }
+
+    /// The JSON schema responses must match, if any
+    pub fn schema(&self) -> Option<&Value> {
+        match self {
+            ResponseFormat::JsonSchema { schema, .. } => Some(schema),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for ResponseFormat {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ResponseFormat::Text => serde_json::json!({ "type": "text" }).serialize(serializer),
+            ResponseFormat::JsonObject => serde_json::json!({ "type": "json_object" }).serialize(serializer),
+            ResponseFormat::JsonSchema { name, schema, strict } => serde_json::json!({
+                "type": "json_schema",
+                "json_schema": { "name": name, "schema": schema, "strict": strict },
+            })
+            .serialize(serializer),
+        }
+    }
+}
+
+/// Token counts reported by the provider.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: u32,
+    #[serde(default)]
+    pub completion_tokens: u32,
+    #[serde(default)]
+    pub total_tokens: u32,
+}
+
+/// A chat response, in the shape of an OpenAI chat completion.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatResponse {
+    /// The generated choices; most requests produce one
+    pub choices: Vec<Choice>,
+    /// The model that answered, if reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Token counts, if reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+impl ChatResponse {
+    /// A response with a single assistant message
+    pub fn from_text(content: impl Into<String>) -> Self {
+        Self {
+            choices: vec![Choice {
+                message: Message {
+                    role: "assistant".to_string(),
+                    content: Some(content.into()),
+                    tool_calls: None,
+                },
+                finish_reason: Some("stop".to_string()),
+            }],
+            ..Self::default()
+        }
+    }
+
+    /// The message of the first choice
+    pub fn message(&self) -> Option<&Message> {
+        self.choices.first().map(|choice| &choice.message)
+    }
+
+    /// The text of the first choice, if any
+    pub fn content(&self) -> Option<&str> {
+        self.message().and_then(|message| message.content.as_deref())
+    }
+
+    /// The tool calls of the first choice
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        self.message()
+            .and_then(|message| message.tool_calls.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+/// Assembles a [`ChatResponse`] from streamed deltas.
+#[derive(Debug, Default)]
+pub(crate) struct ChatAccumulator {
+    content: Option<String>,
+    tool_calls: Vec<ToolCall>,
+    finish_reason: Option<String>,
+    pub(crate) model: Option<String>,
+    pub(crate) usage: Option<Usage>,
+}
+
+impl ChatAccumulator {
+    /// Apply a content or tool call delta; other events are ignored
+    pub(crate) fn apply(&mut self, event: &ChatEvent) {
+        match event {
+            ChatEvent::ContentDelta(delta) => {
+                self.content.get_or_insert_with(String::new).push_str(delta);
+            }
+            ChatEvent::ToolCallDelta { index, id, name, arguments } => {
+                while self.tool_calls.len() <= *index {
+                    self.tool_calls.push(ToolCall {
+                        id: String::new(),
+                        call_type: "function".to_string(),
+                        function: FunctionCall {
+                            name: String::new(),
+                            arguments: String::new(),
+                        },
+                        thought_signature: None,
+                    });
+                }
+                let call = &mut self.tool_calls[*index];
+                if let Some(id) = id {
+                    call.id = id.clone();
+                }
+                if let Some(name) = name {
+                    call.function.name.push_str(name);
+                }
+                call.function.arguments.push_str(arguments);
+            }
+            ChatEvent::Done(_) => {}
+        }
+    }
+
+    /// Record why the model stopped
+    pub(crate) fn set_finish_reason(&mut self, finish_reason: String) {
+        self.finish_reason = Some(finish_reason);
+    }
+
+    /// The response assembled so far
+    pub(crate) fn finish(self) -> ChatResponse {
+        let tool_calls = if self.tool_calls.is_empty() { None } else { Some(self.tool_calls) };
+        ChatResponse {
+            choices: vec![Choice {
+                message: Message {
+                    role: "assistant".to_string(),
+                    content: self.content,
+                    tool_calls,
+                },
+                finish_reason: self.finish_reason,
+            }],
+            model: self.model,
+            usage: self.usage,
+        }
+    }
+}
+
+impl From<ChatResponse> for LLMResponse {
+    fn from(response: ChatResponse) -> Self {
+        LLMResponse {
+            choices: response.choices,
+        }
+    }
+}
+
+/// The JSON schema of a tool's parameters, without the unset optional
+/// fields that stricter providers reject as `null`.
+pub(crate) fn parameters_schema(parameters: &Parameters) -> Value {
+    fn strip_nulls(value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                map.retain(|_, value| !value.is_null());
+                map.values_mut().for_each(strip_nulls);
+            }
+            Value::Array(values) => values.iter_mut().for_each(strip_nulls),
+            _ => {}
+        }
+    }
+    let mut schema = serde_json::to_value(parameters).unwrap_or_default();
+    strip_nulls(&mut schema);
+    schema
+}
+
+/// Parse the JSON arguments of a tool call for providers that take them as an
+/// object. Empty arguments are an empty object.
+pub(crate) fn arguments_value(arguments: &str) -> LLMGraphResult<Value> {
+    if arguments.trim().is_empty() {
+        return Ok(Value::Object(Default::default()));
+    }
+    serde_json::from_str(arguments)
+        .map_err(|e| ApiError::RequestFailed(format!("Tool call arguments are not valid JSON: {}", e)).into())
+}
+
+/// The HTTP client shared by all provider clients that don't bring their own,
+/// so they share one connection pool.
+pub(crate) fn shared_http_client() -> Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT.get_or_init(Client::new).clone()
+}
+
+/// The JSON body of a request: `body` with the request's `extra` fields,
+/// which replace fields of the same name.
+pub(crate) fn json_body(body: &impl Serialize, extra: &Map<String, Value>) -> LLMGraphResult<Value> {
+    // Going through text keeps `f32` fields such as a temperature of 0.2
+    // from being widened to 0.20000000298023224
+    let mut body: Value = serde_json::from_str(&serde_json::to_string(body)?)?;
+    if let Value::Object(fields) = &mut body {
+        fields.extend(extra.iter().map(|(key, value)| (key.clone(), value.clone())));
+    }
+    Ok(body)
+}
+
+/// The `reqwest` client and request timeout of a provider client
+#[derive(Debug, Clone)]
+pub(crate) struct HttpConfig {
+    pub(crate) client: Client,
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            client: shared_http_client(),
+            timeout: None,
+        }
+    }
+}
+
+impl HttpConfig {
+    /// A request to `url` that fails with [`ApiError::Timeout`] after the timeout
+    pub(crate) fn request(&self, method: Method, url: impl reqwest::IntoUrl) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, url);
+        match self.timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
+}
+
+/// Pairs tool messages with the tool calls they answer, for providers that
+/// identify the call in each tool result.
+#[derive(Default)]
+pub(crate) struct PendingToolCalls<'a> {
+    /// Tool calls of the last assistant message not yet answered by a tool message
+    calls: VecDeque<&'a ToolCall>,
+}
+
+impl<'a> PendingToolCalls<'a> {
+    /// Wait for answers to the tool calls of an assistant message
+    pub(crate) fn expect(&mut self, message: &'a Message) {
+        self.calls.clear();
+        self.calls.extend(message.tool_calls.iter().flatten());
+    }
+
+    /// The tool call the next tool message answers
+    pub(crate) fn answer(&mut self) -> LLMGraphResult<&'a ToolCall> {
+        self.calls.pop_front().ok_or_else(|| {
+            ApiError::RequestFailed("Tool message does not answer a preceding tool call".to_string()).into()
+        })
+    }
+}
+
+/// Merge consecutive turns of one role and drop empty turns, for providers
+/// that require roles to alternate. The results of several tool calls, for
+/// example, become a single user turn.
+pub(crate) fn merge_turns<T>(turns: Vec<(&'static str, Vec<T>)>) -> Vec<(&'static str, Vec<T>)> {
+    let mut merged: Vec<(&'static str, Vec<T>)> = Vec::new();
+    for (role, parts) in turns {
+        match merged.last_mut() {
+            Some((last, last_parts)) if *last == role => last_parts.extend(parts),
+            _ if parts.is_empty() => {}
+            _ => merged.push((role, parts)),
+        }
+    }
+    merged
+}
+
+/// Send `request` and return the response if its status is successful.
+///
+/// Unsuccessful statuses are mapped with [`ApiError::from_status`].
+pub(crate) async fn send(request: reqwest::RequestBuilder) -> LLMGraphResult<reqwest::Response> {
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        let status_code = response.status();
+        let error_text = response.text().await.unwrap_or_else(|_| "No error details".to_string());
+        return Err(ApiError::from_status(status_code.as_u16(), error_text).into());
+    }
+    Ok(response)
+}
+
+/// Send `request` and decode the JSON body of a successful response.
+pub(crate) async fn send_json<T: DeserializeOwned>(request: reqwest::RequestBuilder) -> LLMGraphResult<T> {
+    send(request)
+        .await?
+        .json()
+        .await
+        .map_err(|e| {
+            let message = format!("Failed to parse JSON response: {}", e.without_url());
+            ApiError::InvalidResponse(message).into()
+        })
+}
@@ -0,0 +1,457 @@
+//! Client for Google's Gemini `generateContent` API.
+
+use async_trait::async_trait;
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+use crate::errors::{ApiError, LLMGraphResult};
+use crate::generate::client::{
+    arguments_value, json_body, merge_turns, parameters_schema, send_json, ChatRequest, ChatResponse, HttpConfig, LlmClient,
+    PendingToolCalls, ResponseFormat, ToolChoice, Usage,
+};
+use crate::models::tools::{Choice, FunctionCall, Message, Tool, ToolCall};
+
+/// The request body of a `generateContent` call
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContent<'a> {
+    contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<GeminiTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_config: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GenerationConfig<'a>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Content {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+/// A part of a content; exactly one of `text`, `function_call` and
+/// `function_response` is set
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Part {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_call: Option<GeminiFunctionCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_response: Option<FunctionResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thought_signature: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GeminiFunctionCall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FunctionResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    name: String,
+    response: Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiTool {
+    function_declarations: Vec<FunctionDeclaration>,
+}
+
+#[derive(Serialize)]
+struct FunctionDeclaration {
+    name: String,
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parameters: Option<Value>,
+}
+
+/// Sampling parameters; unset parameters use the model's defaults
+#[derive(Serialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_json_schema: Option<&'a Value>,
+}
+
+/// The response body of a `generateContent` call
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    prompt_feedback: Option<PromptFeedback>,
+    usage_metadata: Option<UsageMetadata>,
+    model_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: Option<Content>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    #[serde(default)]
+    total_token_count: u32,
+}
+
+/// An [`LlmClient`] for Gemini's `models/{model}:generateContent` endpoint.
+///
+/// Requests and responses are translated from and to llmgraph's OpenAI-style
+/// messages:
+///
+/// - `system` messages become the `systemInstruction`, `assistant` messages
+///   `model` contents, and other messages `user` contents
+/// - tools become `functionDeclarations`
+/// - `functionCall` parts become tool calls, and `tool` messages become
+///   `functionResponse` parts answering the preceding tool calls in order.
+///   Tool call IDs are echoed in both, and a `functionCall` part's
+///   `thoughtSignature` is kept in [`ToolCall::thought_signature`] and sent
+///   back with the call
+/// - `finishReason` maps onto `finish_reason` (`STOP` becomes `stop`, or
+///   `tool_calls` when the model called functions, `MAX_TOKENS` becomes
+///   `length`, and safety stops become `content_filter`)
+/// - sampling parameters and JSON response formats go in `generationConfig`
+///   (as `responseMimeType` and `responseJsonSchema`), and `tool_choice` becomes
+///   the `toolConfig` function calling mode; `parallel_tool_calls` and
+///   `user` are not supported and are ignored
+///
+/// The API key is sent in the `x-goog-api-key` header, or in the `key` query
+/// parameter with [`GeminiClient::with_key_in_query`].
+///
+/// # Example
+/// ```rust,no_run
+/// use llmgraph::generate::client::{ChatRequest, LlmClient};
+/// use llmgraph::generate::gemini::GeminiClient;
+/// use llmgraph::Message;
+///
+/// # async fn example() -> llmgraph::LLMGraphResult<()> {
+/// let client = GeminiClient::new("AIza...").with_model("gemini-2.5-flash");
+///
+/// let request = ChatRequest::new(vec![Message {
+///     role: "user".to_string(),
+///     content: Some("Hello!".to_string()),
+///     tool_calls: None,
+/// }]);
+/// let response = client.chat(request).await?;
+/// println!("{}", response.content().unwrap_or_default());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GeminiClient {
+    http: HttpConfig,
+    base_url: String,
+    api_key: String,
+    key_in_query: bool,
+    model: Option<String>,
+}
+
+impl GeminiClient {
+    /// Create a client for the Gemini API, authenticating with `api_key`
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            http: HttpConfig::default(),
+            base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
+            api_key: api_key.into(),
+            key_in_query: false,
+            model: None,
+        }
+    }
+
+    /// Send requests to another API root, e.g. a proxy or a stub server
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Send the API key as the `key` query parameter instead of a header
+    pub fn with_key_in_query(mut self) -> Self {
+        self.key_in_query = true;
+        self
+    }
+
+    /// Set the model used for requests that don't name one
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Fail `generateContent` calls that take longer than `timeout` with
+    /// [`ApiError::Timeout`]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http.timeout = Some(timeout);
+        self
+    }
+
+    /// Send requests with `http` instead of the client shared by all providers
+    pub fn with_http_client(mut self, http: Client) -> Self {
+        self.http.client = http;
+        self
+    }
+
+    /// The URL content is generated at for `model`
+    pub fn generate_url(&self, model: &str) -> String {
+        let model = model.strip_prefix("models/").unwrap_or(model);
+        format!("{}/models/{}:generateContent", self.base_url.trim_end_matches('/'), model)
+    }
+}
+
+#[async_trait]
+impl LlmClient for GeminiClient {
+    async fn chat(&self, request: ChatRequest) -> LLMGraphResult<ChatResponse> {
+        let model = request.model_or(&self.model)?;
+        let (system_instruction, contents) = to_gemini_contents(&request.messages)?;
+        let declarations: Vec<FunctionDeclaration> = request.tools.iter().flatten().map(to_declaration).collect();
+        let body = GenerateContent {
+            contents,
+            system_instruction,
+            tools: if declarations.is_empty() {
+                Vec::new()
+            } else {
+                vec![GeminiTool { function_declarations: declarations }]
+            },
+            tool_config: request.tool_choice.as_ref().map(to_tool_config),
+            generation_config: to_generation_config(&request),
+        };
+
+        let http_request = self
+            .http
+            .request(Method::POST, self.generate_url(model))
+            .json(&json_body(&body, &request.extra)?);
+        let http_request = if self.key_in_query {
+            http_request.query(&[("key", &self.api_key)])
+        } else {
+            http_request.header("x-goog-api-key", &self.api_key)
+        };
+        let response: GenerateContentResponse = send_json(http_request).await?;
+        from_gemini_response(response)
+    }
+}
+
+/// The sampling parameters of `request`, if it sets any
+fn to_generation_config(request: &ChatRequest) -> Option<GenerationConfig<'_>> {
+    let config = GenerationConfig {
+        temperature: request.temperature,
+        max_output_tokens: request.max_tokens,
+        top_p: request.top_p,
+        stop_sequences: request.stop.as_deref(),
+        seed: request.seed,
+        presence_penalty: request.presence_penalty,
+        frequency_penalty: request.frequency_penalty,
+        response_mime_type: match request.response_format {
+            Some(ResponseFormat::JsonObject | ResponseFormat::JsonSchema { .. }) => Some("application/json"),
+            Some(ResponseFormat::Text) | None => None,
+        },
+        response_json_schema: request.response_format.as_ref().and_then(ResponseFormat::schema),
+    };
+    if config == GenerationConfig::default() { None } else { Some(config) }
+}
+
+/// The `toolConfig` for a tool choice
+fn to_tool_config(tool_choice: &ToolChoice) -> Value {
+    let config = match tool_choice {
+        ToolChoice::Auto => json!({ "mode": "AUTO" }),
+        ToolChoice::None => json!({ "mode": "NONE" }),
+        ToolChoice::Required => json!({ "mode": "ANY" }),
+        ToolChoice::Function(name) => json!({ "mode": "ANY", "allowedFunctionNames": [name] }),
+    };
+    json!({ "functionCallingConfig": config })
+}
+
+fn to_declaration(tool: &Tool) -> FunctionDeclaration {
+    // Gemini rejects object schemas without properties
+    let parameters = &tool.function.parameters;
+    FunctionDeclaration {
+        name: tool.function.name.clone(),
+        description: tool.function.description.clone(),
+        parameters: if parameters.properties.is_empty() {
+            None
+        } else {
+            Some(parameters_schema(parameters))
+        },
+    }
+}
+
+/// Split off the system instruction and convert the conversation to contents
+fn to_gemini_contents(messages: &[Message]) -> LLMGraphResult<(Option<Content>, Vec<Content>)> {
+    let mut system = Vec::new();
+    let mut turns = Vec::with_capacity(messages.len());
+    let mut pending = PendingToolCalls::default();
+
+    for message in messages {
+        let text = message.content.clone().filter(|text| !text.is_empty());
+        let text_part = |text| Part { text: Some(text), ..Part::default() };
+        let turn = match message.role.as_str() {
+            "system" => {
+                system.extend(text.map(text_part));
+                continue;
+            }
+            "assistant" => {
+                let mut parts: Vec<Part> = text.map(text_part).into_iter().collect();
+                pending.expect(message);
+                for call in message.tool_calls.iter().flatten() {
+                    parts.push(Part {
+                        function_call: Some(GeminiFunctionCall {
+                            id: call_id(call),
+                            name: call.function.name.clone(),
+                            args: arguments_value(&call.function.arguments)?,
+                        }),
+                        thought_signature: call.thought_signature.clone(),
+                        ..Part::default()
+                    });
+                }
+                ("model", parts)
+            }
+            "tool" => {
+                let call = pending.answer()?;
+                let part = Part {
+                    function_response: Some(FunctionResponse {
+                        id: call_id(call),
+                        name: call.function.name.clone(),
+                        response: function_response(message.content.as_deref().unwrap_or_default()),
+                    }),
+                    ..Part::default()
+                };
+                ("user", vec![part])
+            }
+            _ => ("user", text.map(text_part).into_iter().collect()),
+        };
+        turns.push(turn);
+    }
+
+    let system = if system.is_empty() { None } else { Some(Content { role: None, parts: system }) };
+    let contents = merge_turns(turns)
+        .into_iter()
+        .map(|(role, parts)| Content {
+            role: Some(role.to_string()),
+            parts,
+        })
+        .collect();
+    Ok((system, contents))
+}
+
+/// The ID of a tool call as sent back to Gemini, if it has one
+fn call_id(call: &ToolCall) -> Option<String> {
+    Some(call.id.clone()).filter(|id| !id.is_empty())
+}
+
+/// The `response` object of a function response; tool results that aren't
+/// JSON objects are wrapped as `{"result": ...}`
+fn function_response(content: &str) -> Value {
+    match serde_json::from_str(content) {
+        Ok(Value::Object(object)) => Value::Object(object),
+        Ok(value) => json!({ "result": value }),
+        Err(_) => json!({ "result": content }),
+    }
+}
+
+/// Convert a `generateContent` response, with a choice per candidate
+fn from_gemini_response(response: GenerateContentResponse) -> LLMGraphResult<ChatResponse> {
+    if response.candidates.is_empty() {
+        if let Some(reason) = response.prompt_feedback.and_then(|feedback| feedback.block_reason) {
+            return Err(ApiError::InvalidResponse(format!("Prompt was blocked: {}", reason)).into());
+        }
+    }
+
+    let choices = response
+        .candidates
+        .into_iter()
+        .map(|candidate| {
+            let mut text = String::new();
+            let mut tool_calls = Vec::new();
+            for part in candidate.content.map(|content| content.parts).unwrap_or_default() {
+                if let Some(part_text) = part.text {
+                    text.push_str(&part_text);
+                }
+                if let Some(call) = part.function_call {
+                    tool_calls.push(ToolCall {
+                        id: call.id.unwrap_or_else(|| format!("call_{}", tool_calls.len())),
+                        call_type: "function".to_string(),
+                        function: FunctionCall {
+                            name: call.name,
+                            arguments: call.args.to_string(),
+                        },
+                        thought_signature: part.thought_signature,
+                    });
+                }
+            }
+
+            let finish_reason = candidate.finish_reason.map(|reason| {
+                match reason.as_str() {
+                    "STOP" if !tool_calls.is_empty() => "tool_calls".to_string(),
+                    "STOP" => "stop".to_string(),
+                    "MAX_TOKENS" => "length".to_string(),
+                    "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" => {
+                        "content_filter".to_string()
+                    }
+                    other => other.to_lowercase(),
+                }
+            });
+            let content = if text.is_empty() && !tool_calls.is_empty() { None } else { Some(text) };
+            Choice {
+                message: Message {
+                    role: "assistant".to_string(),
+                    content,
+                    tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+                },
+                finish_reason,
+            }
+        })
+        .collect();
+
+    Ok(ChatResponse {
+        choices,
+        model: response.model_version,
+        usage: response.usage_metadata.map(|usage| Usage {
+            prompt_tokens: usage.prompt_token_count,
+            completion_tokens: usage.candidates_token_count,
+            total_tokens: usage.total_token_count,
+        }),
+    })
+}
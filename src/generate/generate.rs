@@ -1,25 +1,20 @@
 //! LLM API integration module for generating responses with OpenRouter.
 //!
 //! This module provides functions to interact with LLM APIs, supporting
-//! both simple text generation and function calling capabilities.
+//! both simple text generation and function calling capabilities. They are
+//! shims over [`OpenAiCompatibleClient`]; build a client once to reuse its
+//! connections, or program against [`LlmClient`] to switch providers.
+//! [`generate_structured`] works with any [`LlmClient`] that supports JSON
+//! schema response formats; clients without them only get its parse-and-retry
+//! loop.
 
-use reqwest::{header::{HeaderMap, HeaderValue}, Client};
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::errors::{ApiError, LLMGraphResult};
+use crate::generate::client::{ChatRequest, ChatStream, LlmClient, ResponseFormat};
+use crate::generate::openai::OpenAiCompatibleClient;
 use crate::models::tools::*;
 
-#[derive(Serialize)]
-struct ChatCompletion {
-    model: String,
-    messages: Vec<Message>,
-    temperature: f32,
-    tools: Option<Vec<Tool>>,
-}
-
-#[derive(Deserialize)]
-struct APIResponse {
-    choices: Vec<Choice>,
-}
-
 /// Generate a text response from an LLM without tool support.
 ///
 /// # Arguments
@@ -31,7 +26,7 @@ struct APIResponse {
 ///
 /// # Returns
 /// * `Ok(String)` - The generated text response
-/// * `Err(reqwest::Error)` - If the API request fails
+/// * `Err(LLMGraphError::ApiError)` - If the API request fails
 ///
 /// # Example
 /// ```rust,ignore
@@ -52,7 +47,7 @@ pub async fn generate(
     model: String,
     temperature: f32,
     messages: Vec<Message>
-) -> Result<String, reqwest::Error> {
+) -> LLMGraphResult<String> {
     generate_with_tools(base_url, api_key, model, temperature, messages, None).await
 }
 
@@ -68,7 +63,7 @@ pub async fn generate(
 ///
 /// # Returns
 /// * `Ok(String)` - The generated text response
-/// * `Err(reqwest::Error)` - If the API request fails
+/// * `Err(LLMGraphError::ApiError)` - If the API request fails
 pub async fn generate_with_tools(
     base_url: String,
     api_key: String,
@@ -76,48 +71,15 @@ pub async fn generate_with_tools(
     temperature: f32,
     messages: Vec<Message>,
     tools: Option<Vec<Tool>>
-) -> Result<String, reqwest::Error> {
-    let content_type: &str = "application/json";
-
-    let mut headers = HeaderMap::new();
-
-    headers.insert(
-        "authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key))
-            .expect("Failed to add api key to Bearer"),
-    );
-
-    headers.insert(
-        "content-type",
-        HeaderValue::from_str(content_type).expect("Failed to add content-type")
-    );
-
-    let client = Client::builder().default_headers(headers).build().expect("Can't build client");
-
-    let chat_completion = ChatCompletion {
-        model,
-        messages,
-        temperature,
-        tools,
-    };
-
-    let response = client
-        .post(base_url)
-        .json(&chat_completion)
-        .send()
-        .await?;
+) -> LLMGraphResult<String> {
+    let response = generate_full_response(base_url, api_key, model, temperature, messages, tools).await?;
 
-    let res: APIResponse = response.json().await?;
-
-    // Extract the content from the first choice
-    if let Some(choice) = res.choices.first() {
-        if let Some(content) = &choice.message.content {
-            return Ok(content.clone());
-        }
-    }
-
-    // If there's no content, return an empty string
-    Ok(String::new())
+    // Extract the content from the first choice, or an empty string
+    Ok(response
+        .choices
+        .first()
+        .and_then(|choice| choice.message.content.clone())
+        .unwrap_or_default())
 }
 
 /// Generate a full response from an LLM including tool calls.
@@ -135,7 +97,8 @@ pub async fn generate_with_tools(
 ///
 /// # Returns
 /// * `Ok(LLMResponse)` - The full response including tool calls
-/// * `Err(String)` - Error message with status information if the API request fails
+/// * `Err(LLMGraphError::ApiError)` - If the API request fails; unsuccessful
+///   statuses are mapped with [`ApiError::from_status`](crate::errors::ApiError::from_status)
 ///
 /// # Example
 /// ```rust,ignore
@@ -163,52 +126,141 @@ pub async fn generate_full_response(
     temperature: f32,
     messages: Vec<Message>,
     tools: Option<Vec<Tool>>
-) -> Result<LLMResponse, String> {
-    let content_type: &str = "application/json";
-
-    let mut headers = HeaderMap::new();
-
-    headers.insert(
-        "authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key))
-            .map_err(|e| format!("Failed to create auth header: {}", e))?,
-    );
-
-    headers.insert(
-        "content-type",
-        HeaderValue::from_str(content_type)
-            .map_err(|e| format!("Failed to create content-type header: {}", e))?
-    );
+) -> LLMGraphResult<LLMResponse> {
+    let client = shim_client(base_url, api_key);
+    let mut request = ChatRequest::new(messages)
+        .with_model(model)
+        .with_temperature(temperature);
+    request.tools = tools;
+    Ok(client.chat(request).await?.into())
+}
 
-    let client = Client::builder()
-        .default_headers(headers)
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+/// Stream a response from an LLM as it is generated.
+///
+/// The stream yields content and tool call deltas and ends with
+/// [`ChatEvent::Done`](crate::generate::client::ChatEvent::Done) holding the
+/// assembled response, which converts into an [`LLMResponse`].
+///
+/// # Example
+/// ```rust,ignore
+/// # use llmgraph::generate::client::ChatEvent;
+/// # use llmgraph::generate::generate::generate_stream;
+/// # use futures::StreamExt;
+/// # // In async context with defined variables
+/// let mut stream = generate_stream(base_url, api_key, model, 0.7, messages, None).await?;
+/// while let Some(event) = stream.next().await {
+///     match event? {
+///         ChatEvent::ContentDelta(delta) => print!("{}", delta),
+///         ChatEvent::Done(response) => println!("\n{:?}", response.usage),
+///         _ => {}
+///     }
+/// }
+/// ```
+pub async fn generate_stream(
+    base_url: String,
+    api_key: String,
+    model: String,
+    temperature: f32,
+    messages: Vec<Message>,
+    tools: Option<Vec<Tool>>
+) -> LLMGraphResult<ChatStream> {
+    let client = shim_client(base_url, api_key);
+    let mut request = ChatRequest::new(messages)
+        .with_model(model)
+        .with_temperature(temperature);
+    request.tools = tools;
+    client.chat_stream(request).await
+}
 
-    let chat_completion = ChatCompletion {
-        model,
-        messages,
-        temperature,
-        tools,
-    };
+/// The client behind the functions above, which post to `base_url` exactly
+/// as given.
+fn shim_client(base_url: String, api_key: String) -> OpenAiCompatibleClient {
+    OpenAiCompatibleClient::new(base_url.clone())
+        .with_endpoint(base_url)
+        .with_api_key(api_key)
+}
 
-    let response = client
-        .post(base_url)
-        .json(&chat_completion)
-        .send()
-        .await
-        .map_err(|e| format!("HTTP request failed: {}", e))?;
+/// Generate a response matching a JSON schema and decode it into `T`.
+///
+/// The request is sent with a `json_schema` response format named `name`,
+/// which the built-in clients translate for their provider (for Anthropic, a
+/// forced tool call).
+/// The text of the first choice is decoded into `T`; when that fails, the
+/// model's answer and the parse error are appended to the conversation and
+/// the model is asked again, up to `max_retries` times.
+///
+/// # Returns
+/// * `Ok(T)` - The decoded response
+/// * `Err(LLMGraphError::ApiError)` - If a request fails, or with
+///   `ApiError::InvalidResponse` if no answer could be decoded
+///
+/// # Example
+/// ```rust,ignore
+/// # use llmgraph::generate::generate::generate_structured;
+/// # use llmgraph::ChatRequest;
+/// # use serde::Deserialize;
+/// # use serde_json::json;
+/// #[derive(Deserialize)]
+/// struct Weather {
+///     city: String,
+///     celsius: f64,
+/// }
+///
+/// # // In async context with defined variables
+/// let schema = json!({
+///     "type": "object",
+///     "properties": {"city": {"type": "string"}, "celsius": {"type": "number"}},
+///     "required": ["city", "celsius"],
+///     "additionalProperties": false
+/// });
+/// let weather: Weather = generate_structured(&client, ChatRequest::new(messages), "weather", schema, 2).await?;
+/// ```
+pub async fn generate_structured<T: DeserializeOwned>(
+    client: &dyn LlmClient,
+    request: ChatRequest,
+    name: &str,
+    schema: Value,
+    max_retries: usize
+) -> LLMGraphResult<T> {
+    let mut request = request.with_response_format(ResponseFormat::json_schema(name, schema));
+    let mut attempt = 0;
+    loop {
+        let response = client.chat(request.clone()).await?;
+        let content = response.content().unwrap_or_default().to_string();
+        let error = match serde_json::from_str::<T>(strip_code_fence(&content)) {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        if attempt == max_retries {
+            let message = format!("Response did not match the schema after {} attempts: {}", attempt + 1, error);
+            return Err(ApiError::InvalidResponse(message).into());
+        }
+        attempt += 1;
 
-    // Check if the response was successful
-    if !response.status().is_success() {
-        let status_code = response.status();
-        let error_text = response.text().await.unwrap_or_else(|_| "No error details".to_string());
-        return Err(format!("API request failed with status {}: {}", status_code, error_text));
+        // Show the model its answer and why it was rejected
+        request.messages.push(Message {
+            role: "assistant".to_string(),
+            content: Some(content),
+            tool_calls: None,
+        });
+        request.messages.push(Message {
+            role: "user".to_string(),
+            content: Some(format!(
+                "Your response could not be parsed: {}. Reply with only JSON matching the schema.",
+                error
+            )),
+            tool_calls: None,
+        });
     }
+}
 
-    let res: LLMResponse = response.json()
-        .await
-        .map_err(|e| format!("Failed to parse JSON response: {}", e))?;
-    
-    Ok(res)
-}
\ No newline at end of file
+/// The JSON inside a Markdown code fence, which some models add despite the
+/// response format
+fn strip_code_fence(content: &str) -> &str {
+    let content = content.trim();
+    content
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(|rest| rest.strip_prefix("json").unwrap_or(rest).trim())
+        .unwrap_or(content)
+}
@@ -1 +1,8 @@
-pub mod generate;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod generate;
+pub mod client;
+pub mod openai;
+pub mod anthropic;
+pub mod gemini;
+pub mod ollama;
+pub(crate) mod sse;
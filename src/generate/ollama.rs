@@ -0,0 +1,351 @@
+//! Client for Ollama's native chat API.
+
+use async_trait::async_trait;
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use crate::errors::LLMGraphResult;
+use crate::generate::client::{
+    arguments_value, json_body, parameters_schema, send_json, ChatRequest, ChatResponse, HttpConfig, LlmClient, PendingToolCalls, ResponseFormat,
+    Usage,
+};
+use crate::models::tools::{Choice, FunctionCall, Message, Tool, ToolCall};
+
+/// The request body of an `/api/chat` call
+#[derive(Serialize)]
+struct OllamaChat<'a> {
+    model: &'a str,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<OllamaTool<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<OllamaOptions<'a>>,
+    /// `"json"` or a JSON schema the response must match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct OllamaTool<'a> {
+    #[serde(rename = "type")]
+    tool_type: &'static str,
+    function: OllamaFunction<'a>,
+}
+
+#[derive(Serialize)]
+struct OllamaFunction<'a> {
+    name: &'a str,
+    description: &'a str,
+    parameters: Value,
+}
+
+/// Sampling options; unset options use the model's defaults
+#[derive(Serialize, Default, PartialEq)]
+struct OllamaOptions<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OllamaToolCall>,
+    /// The tool a `tool` message answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
+}
+
+/// A tool call, whose arguments are an object rather than a JSON string
+#[derive(Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunctionCall,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+/// The response body of an `/api/chat` call
+#[derive(Deserialize)]
+struct OllamaChatResponse {
+    model: Option<String>,
+    message: OllamaMessage,
+    done_reason: Option<String>,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<OllamaModel>,
+}
+
+/// A model available on an Ollama server, as listed by [`OllamaClient::list_models`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaModel {
+    /// The model name to request, e.g. `llama3.1:8b`
+    pub name: String,
+    /// The size of the model on disk in bytes
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// The digest of the model's weights
+    #[serde(default)]
+    pub digest: Option<String>,
+    /// When the model was last modified, as an RFC 3339 timestamp
+    #[serde(default)]
+    pub modified_at: Option<String>,
+}
+
+/// An [`LlmClient`] for Ollama's native `/api/chat` endpoint.
+///
+/// No API key is needed; [`OllamaClient::with_api_key`] only adds a Bearer
+/// token for servers behind an authenticating proxy. Tool calls use Ollama's
+/// format, with arguments as objects, and tools are sent as Ollama's
+/// `{"type": "function", "function": {...}}` declarations; as Ollama assigns
+/// no tool call IDs, calls are given the IDs `call_0`, `call_1`, ... in
+/// order, and each `tool` message is sent with the name of the preceding
+/// tool call it answers. Sampling parameters are sent as `options`, with
+/// `max_tokens` as `num_predict`, and JSON response formats as `format`;
+/// `tool_choice`, `parallel_tool_calls` and `user` are not supported and are
+/// ignored.
+///
+/// For llama.cpp and other servers with an OpenAI-compatible API, use
+/// [`OpenAiCompatibleClient`](crate::generate::openai::OpenAiCompatibleClient)
+/// without an API key instead.
+///
+/// # Example
+/// ```rust,no_run
+/// use llmgraph::generate::client::{ChatRequest, LlmClient};
+/// use llmgraph::generate::ollama::OllamaClient;
+/// use llmgraph::Message;
+///
+/// # async fn example() -> llmgraph::LLMGraphResult<()> {
+/// let client = OllamaClient::new("http://localhost:11434");
+/// let model = client.list_models().await?.remove(0);
+///
+/// let request = ChatRequest::new(vec![Message {
+///     role: "user".to_string(),
+///     content: Some("Hello!".to_string()),
+///     tool_calls: None,
+/// }]);
+/// let response = client.chat(request.with_model(model.name)).await?;
+/// println!("{}", response.content().unwrap_or_default());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OllamaClient {
+    http: HttpConfig,
+    base_url: String,
+    api_key: Option<String>,
+    model: Option<String>,
+}
+
+impl OllamaClient {
+    /// Create a client for the Ollama server at `base_url`, e.g. `http://localhost:11434`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: HttpConfig::default(),
+            base_url: base_url.into(),
+            api_key: None,
+            model: None,
+        }
+    }
+
+    /// Authenticate with a Bearer token, for servers behind a proxy
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Set the model used for requests that don't name one
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Fail chat and model list requests that take longer than `timeout` with
+    /// [`ApiError::Timeout`](crate::errors::ApiError::Timeout); local models
+    /// can take a while to load
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http.timeout = Some(timeout);
+        self
+    }
+
+    /// Send requests with `http` instead of the client shared by all providers
+    pub fn with_http_client(mut self, http: Client) -> Self {
+        self.http.client = http;
+        self
+    }
+
+    /// List the models available on the server, from `/api/tags`
+    pub async fn list_models(&self) -> LLMGraphResult<Vec<OllamaModel>> {
+        let list: ModelList = send_json(self.request(Method::GET, "/api/tags")).await?;
+        Ok(list.models)
+    }
+
+    /// Build a request to `path` with the client's auth and timeout
+    fn request(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}{}", self.base_url.trim_end_matches('/'), path);
+        let request = self.http.request(method, url);
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for OllamaClient {
+    async fn chat(&self, request: ChatRequest) -> LLMGraphResult<ChatResponse> {
+        let model = request.model_or(&self.model)?;
+        let body = OllamaChat {
+            model,
+            messages: to_ollama_messages(&request.messages)?,
+            stream: false,
+            tools: request.tools.iter().flatten().map(to_ollama_tool).collect(),
+            options: to_ollama_options(&request),
+            format: match &request.response_format {
+                Some(ResponseFormat::JsonObject) => Some(Value::String("json".to_string())),
+                Some(ResponseFormat::JsonSchema { schema, .. }) => Some(schema.clone()),
+                Some(ResponseFormat::Text) | None => None,
+            },
+        };
+        let http_request = self.request(Method::POST, "/api/chat").json(&json_body(&body, &request.extra)?);
+        let response: OllamaChatResponse = send_json(http_request).await?;
+        Ok(from_ollama_response(response))
+    }
+}
+
+fn to_ollama_tool(tool: &Tool) -> OllamaTool<'_> {
+    OllamaTool {
+        tool_type: "function",
+        function: OllamaFunction {
+            name: &tool.function.name,
+            description: &tool.function.description,
+            parameters: parameters_schema(&tool.function.parameters),
+        },
+    }
+}
+
+/// The sampling options of `request`, if it sets any
+fn to_ollama_options(request: &ChatRequest) -> Option<OllamaOptions<'_>> {
+    let options = OllamaOptions {
+        temperature: request.temperature,
+        num_predict: request.max_tokens,
+        top_p: request.top_p,
+        stop: request.stop.as_deref(),
+        seed: request.seed,
+        presence_penalty: request.presence_penalty,
+        frequency_penalty: request.frequency_penalty,
+    };
+    if options == OllamaOptions::default() { None } else { Some(options) }
+}
+
+/// Convert the conversation to Ollama messages, naming the tool each tool
+/// message answers
+fn to_ollama_messages(messages: &[Message]) -> LLMGraphResult<Vec<OllamaMessage>> {
+    let mut pending = PendingToolCalls::default();
+    let mut converted = Vec::with_capacity(messages.len());
+    for message in messages {
+        let mut tool_calls = Vec::new();
+        let mut tool_name = None;
+        match message.role.as_str() {
+            "assistant" => {
+                pending.expect(message);
+                for call in message.tool_calls.iter().flatten() {
+                    tool_calls.push(OllamaToolCall {
+                        function: OllamaFunctionCall {
+                            name: call.function.name.clone(),
+                            arguments: arguments_value(&call.function.arguments)?,
+                        },
+                    });
+                }
+            }
+            "tool" => tool_name = Some(pending.answer()?.function.name.clone()),
+            _ => {}
+        }
+        converted.push(OllamaMessage {
+            role: message.role.clone(),
+            content: message.content.clone().unwrap_or_default(),
+            tool_calls,
+            tool_name,
+        });
+    }
+    Ok(converted)
+}
+
+/// Convert an `/api/chat` response to a single-choice chat response
+fn from_ollama_response(response: OllamaChatResponse) -> ChatResponse {
+    let tool_calls: Vec<ToolCall> = response
+        .message
+        .tool_calls
+        .into_iter()
+        .enumerate()
+        .map(|(index, call)| ToolCall {
+            id: format!("call_{}", index),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: call.function.name,
+                arguments: call.function.arguments.to_string(),
+            },
+            thought_signature: None,
+        })
+        .collect();
+
+    // Ollama reports "stop" even when the model called tools
+    let finish_reason = if tool_calls.is_empty() {
+        response.done_reason
+    } else {
+        Some("tool_calls".to_string())
+    };
+    let content = if response.message.content.is_empty() && !tool_calls.is_empty() {
+        None
+    } else {
+        Some(response.message.content)
+    };
+    let usage = match (response.prompt_eval_count, response.eval_count) {
+        (None, None) => None,
+        (prompt, completion) => {
+            let (prompt_tokens, completion_tokens) = (prompt.unwrap_or(0), completion.unwrap_or(0));
+            Some(Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            })
+        }
+    };
+    ChatResponse {
+        choices: vec![Choice {
+            message: Message {
+                role: "assistant".to_string(),
+                content,
+                tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+            },
+            finish_reason,
+        }],
+        model: response.model,
+        usage,
+    }
+}
@@ -0,0 +1,372 @@
+//! Client for OpenAI-compatible chat completion APIs (OpenAI, OpenRouter,
+//! vLLM, llama.cpp and others).
+
+use async_trait::async_trait;
+use futures::stream;
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::time::Duration;
+use crate::errors::{ApiError, LLMGraphError, LLMGraphResult};
+use crate::generate::client::{
+    json_body, send, send_json, ChatAccumulator, ChatEvent, ChatRequest, ChatResponse, ChatStream, HttpConfig,
+    LlmClient, ResponseFormat, ToolChoice, Usage,
+};
+use crate::generate::sse::SseParser;
+use crate::models::tools::{Message, Tool};
+
+/// The request body of a chat completion
+#[derive(Serialize)]
+struct ChatCompletion<'a> {
+    model: &'a str,
+    messages: &'a [Message],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<&'a [Tool]>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<&'a ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parallel_tool_calls: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<&'a ResponseFormat>,
+}
+
+/// One server-sent event of a streamed chat completion
+#[derive(Deserialize)]
+struct CompletionChunk {
+    model: Option<String>,
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    usage: Option<Usage>,
+    error: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    index: usize,
+    #[serde(default)]
+    delta: ChunkDelta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct ChunkDelta {
+    content: Option<String>,
+    tool_calls: Option<Vec<ChunkToolCall>>,
+}
+
+#[derive(Deserialize)]
+struct ChunkToolCall {
+    index: usize,
+    id: Option<String>,
+    function: Option<ChunkFunction>,
+}
+
+#[derive(Deserialize)]
+struct ChunkFunction {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+/// The state of a streamed chat completion
+struct CompletionStream {
+    response: reqwest::Response,
+    parser: SseParser,
+    accumulator: ChatAccumulator,
+    pending: VecDeque<LLMGraphResult<ChatEvent>>,
+    finished: bool,
+    /// How long to wait for the next chunk
+    idle_timeout: Option<Duration>,
+}
+
+impl CompletionStream {
+    /// Turn the streamed HTTP response into chat events
+    fn into_events(self) -> ChatStream {
+        Box::pin(stream::unfold(self, |mut state| async move {
+            loop {
+                if let Some(event) = state.pending.pop_front() {
+                    return Some((event, state));
+                }
+                if state.finished {
+                    return None;
+                }
+                let idle_timeout = state.idle_timeout;
+                let chunk = within(idle_timeout, async { Ok(state.response.chunk().await?) }).await;
+                match chunk {
+                    Ok(Some(bytes)) => {
+                        for data in state.parser.push(&bytes) {
+                            state.handle(&data);
+                        }
+                    }
+                    Ok(None) => {
+                        if let Some(data) = state.parser.finish() {
+                            state.handle(&data);
+                        }
+                        state.finish();
+                    }
+                    Err(e) => state.fail(e),
+                }
+            }
+        }))
+    }
+
+    /// Handle the data of one event
+    fn handle(&mut self, data: &str) {
+        if self.finished {
+            return;
+        }
+        if data == "[DONE]" {
+            return self.finish();
+        }
+        let chunk: CompletionChunk = match serde_json::from_str(data) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                let message = format!("Failed to parse stream event: {}", e);
+                return self.fail(ApiError::InvalidResponse(message).into());
+            }
+        };
+        if let Some(error) = chunk.error {
+            return self.fail(ApiError::InvalidResponse(format!("Stream error: {}", error)).into());
+        }
+
+        if chunk.model.is_some() {
+            self.accumulator.model = chunk.model;
+        }
+        if chunk.usage.is_some() {
+            self.accumulator.usage = chunk.usage;
+        }
+        // Only the first choice is streamed
+        for choice in chunk.choices.into_iter().filter(|choice| choice.index == 0) {
+            if let Some(content) = choice.delta.content.filter(|content| !content.is_empty()) {
+                self.emit(ChatEvent::ContentDelta(content));
+            }
+            for call in choice.delta.tool_calls.unwrap_or_default() {
+                let function = call.function.unwrap_or(ChunkFunction { name: None, arguments: None });
+                self.emit(ChatEvent::ToolCallDelta {
+                    index: call.index,
+                    id: call.id,
+                    name: function.name,
+                    arguments: function.arguments.unwrap_or_default(),
+                });
+            }
+            if let Some(finish_reason) = choice.finish_reason {
+                self.accumulator.set_finish_reason(finish_reason);
+            }
+        }
+    }
+
+    /// Queue a delta and add it to the assembled response
+    fn emit(&mut self, event: ChatEvent) {
+        self.accumulator.apply(&event);
+        self.pending.push_back(Ok(event));
+    }
+
+    /// End the stream with the assembled response
+    fn finish(&mut self) {
+        if !self.finished {
+            let response = std::mem::take(&mut self.accumulator).finish();
+            self.pending.push_back(Ok(ChatEvent::Done(response)));
+            self.finished = true;
+        }
+    }
+
+    /// End the stream with an error
+    fn fail(&mut self, error: LLMGraphError) {
+        self.pending.push_back(Err(error));
+        self.finished = true;
+    }
+}
+
+/// An [`LlmClient`] for OpenAI-compatible `/chat/completions` endpoints.
+///
+/// The client is cheap to clone and reuses its connections across requests.
+///
+/// # Example
+/// ```rust,no_run
+/// use llmgraph::generate::client::{ChatRequest, LlmClient};
+/// use llmgraph::generate::openai::OpenAiCompatibleClient;
+/// use llmgraph::Message;
+/// use std::time::Duration;
+///
+/// # async fn example() -> llmgraph::LLMGraphResult<()> {
+/// let client = OpenAiCompatibleClient::new("https://openrouter.ai/api/v1")
+///     .with_api_key("sk-...")
+///     .with_model("openai/gpt-4o-mini")
+///     .with_timeout(Duration::from_secs(60));
+///
+/// let request = ChatRequest::new(vec![Message {
+///     role: "user".to_string(),
+///     content: Some("Hello!".to_string()),
+///     tool_calls: None,
+/// }]);
+/// let response = client.chat(request).await?;
+/// println!("{}", response.content().unwrap_or_default());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OpenAiCompatibleClient {
+    http: HttpConfig,
+    base_url: String,
+    endpoint: Option<String>,
+    api_key: Option<String>,
+    model: Option<String>,
+}
+
+impl OpenAiCompatibleClient {
+    /// Create a client for the API at `base_url`.
+    ///
+    /// `base_url` is either the API root (e.g. `https://api.openai.com/v1`) or
+    /// the full `/chat/completions` endpoint.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: HttpConfig::default(),
+            base_url: base_url.into(),
+            endpoint: None,
+            api_key: None,
+            model: None,
+        }
+    }
+
+    /// Send chat requests to exactly `url` instead of deriving it from the
+    /// base URL, e.g. an Azure deployment URL with an `api-version` query
+    pub fn with_endpoint(mut self, url: impl Into<String>) -> Self {
+        self.endpoint = Some(url.into());
+        self
+    }
+
+    /// Authenticate with a Bearer token
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Set the model used for requests that don't name one
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Fail requests that take longer than `timeout` with [`ApiError::Timeout`].
+    ///
+    /// Streams may run longer: [`LlmClient::chat_stream`] only fails when the
+    /// response headers, or the next chunk of the body, take longer than
+    /// `timeout` to arrive.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http.timeout = Some(timeout);
+        self
+    }
+
+    /// Send requests with `http` instead of the client shared by all
+    /// providers, e.g. one with a connect timeout or a proxy
+    pub fn with_http_client(mut self, http: Client) -> Self {
+        self.http.client = http;
+        self
+    }
+
+    /// The URL chat requests are sent to
+    pub fn chat_url(&self) -> String {
+        if let Some(endpoint) = &self.endpoint {
+            return endpoint.clone();
+        }
+        let base_url = self.base_url.trim_end_matches('/');
+        if base_url.ends_with("/chat/completions") {
+            base_url.to_string()
+        } else {
+            format!("{}/chat/completions", base_url)
+        }
+    }
+
+    /// Add the client's auth to `request`
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+
+    /// The request body for `request`, using the client's default model if
+    /// the request names none, with the request's extra fields
+    fn completion(&self, request: &ChatRequest, stream: bool) -> LLMGraphResult<Value> {
+        let model = request.model_or(&self.model)?;
+        let completion = ChatCompletion {
+            model,
+            messages: &request.messages,
+            temperature: request.temperature,
+            tools: request.tools.as_deref(),
+            stream,
+            max_tokens: request.max_tokens,
+            top_p: request.top_p,
+            stop: request.stop.as_deref(),
+            seed: request.seed,
+            presence_penalty: request.presence_penalty,
+            frequency_penalty: request.frequency_penalty,
+            tool_choice: request.tool_choice.as_ref(),
+            parallel_tool_calls: request.parallel_tool_calls,
+            user: request.user.as_deref(),
+            response_format: request.response_format.as_ref(),
+        };
+        json_body(&completion, &request.extra)
+    }
+}
+
+#[async_trait]
+impl LlmClient for OpenAiCompatibleClient {
+    async fn chat(&self, request: ChatRequest) -> LLMGraphResult<ChatResponse> {
+        let body = self.completion(&request, false)?;
+        let http_request = self.http.request(Method::POST, self.chat_url());
+        send_json(self.authorize(http_request).json(&body)).await
+    }
+
+    /// Stream the completion with `stream: true`, parsing its server-sent events.
+    ///
+    /// The client's timeout applies to the response headers and to each
+    /// chunk of the body, not to the whole stream.
+    async fn chat_stream(&self, request: ChatRequest) -> LLMGraphResult<ChatStream> {
+        let body = self.completion(&request, true)?;
+        let http_request = self.authorize(self.http.client.post(self.chat_url())).json(&body);
+        let response = within(self.http.timeout, send(http_request)).await?;
+        let stream = CompletionStream {
+            response,
+            parser: SseParser::new(),
+            accumulator: ChatAccumulator::default(),
+            pending: VecDeque::new(),
+            finished: false,
+            idle_timeout: self.http.timeout,
+        };
+        Ok(stream.into_events())
+    }
+}
+
+/// Await `future`, failing with [`ApiError::Timeout`] if it takes longer than `timeout`
+async fn within<T>(
+    timeout: Option<Duration>,
+    future: impl Future<Output = LLMGraphResult<T>>,
+) -> LLMGraphResult<T> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future)
+            .await
+            .unwrap_or_else(|_| Err(ApiError::Timeout.into())),
+        None => future.await,
+    }
+}
@@ -0,0 +1,53 @@
+//! Parser for server-sent event streams.
+
+/// Splits a server-sent event stream into the data of its events.
+///
+/// Bytes can be pushed in arbitrary chunks; an event is complete once the
+/// blank line ending it has arrived. Comments and fields other than `data`
+/// are ignored.
+#[derive(Default)]
+pub(crate) struct SseParser {
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add received bytes and return the data of every event they complete
+    pub(crate) fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        // Lines may end with CRLF; dropping CRs leaves LF-separated lines
+        self.buffer.extend(bytes.iter().filter(|&&b| b != b'\r'));
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let event: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            if let Some(data) = event_data(&event) {
+                events.push(data);
+            }
+        }
+        events
+    }
+
+    /// The data of a last event the stream ended without terminating
+    pub(crate) fn finish(&mut self) -> Option<String> {
+        let event = std::mem::take(&mut self.buffer);
+        event_data(&event)
+    }
+}
+
+/// The `data` lines of one event, joined by newlines
+fn event_data(event: &[u8]) -> Option<String> {
+    let event = String::from_utf8_lossy(event);
+    let lines: Vec<&str> = event
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
@@ -10,7 +10,16 @@ pub mod agents;
 
 // Re-export commonly used types for convenience
 pub use errors::{LLMGraphError, LLMGraphResult};
+pub use generate::client::{ChatEvent, ChatRequest, ChatResponse, ChatStream, LlmClient, ResponseFormat, ToolChoice};
+pub use generate::anthropic::AnthropicClient;
+pub use generate::gemini::GeminiClient;
+pub use generate::ollama::OllamaClient;
+pub use generate::openai::OpenAiCompatibleClient;
 pub use models::graph::{Agent, Graph};
+pub use models::run::{CancellationToken, RunContext, RunOptions, RunResult, RunStatus, Step};
+pub use models::checkpoint::{Checkpoint, CheckpointStore};
+pub use models::events::GraphEvent;
+pub use models::response::{AgentAction, AgentResponse};
 pub use models::tools::{Tool, ToolRegistry, ToolRegistryTrait, Message};
 
 // Include comprehensive test module
@@ -0,0 +1,169 @@
+//! Checkpointing for graph runs.
+//!
+//! When a [`CheckpointStore`] is configured with
+//! [`Graph::set_checkpoint_store`](crate::Graph::set_checkpoint_store), the
+//! graph writes a [`Checkpoint`] after every completed step. A run that was
+//! interrupted, e.g. because the process died, can then be continued with
+//! [`Graph::resume`](crate::Graph::resume).
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use crate::errors::{LLMGraphError, LLMGraphResult};
+use crate::models::run::Step;
+
+/// A snapshot of a graph run after a completed step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The ID of the run
+    pub run_id: String,
+    /// The node to run next (None once the run has finished)
+    pub next_node: Option<i32>,
+    /// The input for the next node
+    pub pending_input: String,
+    /// The run state after the last completed step
+    pub state: Map<String, Value>,
+    /// Every step completed so far
+    pub steps: Vec<Step>,
+    /// Agent snapshots (see [`Agent::snapshot`](crate::Agent::snapshot)) by node ID
+    pub agent_states: HashMap<i32, String>,
+    /// The prompt of the interrupt the run is paused at, if any
+    #[serde(default)]
+    pub interrupt: Option<String>,
+}
+
+/// Trait for implementing checkpoint storage backends.
+///
+/// # Example
+/// ```rust
+/// use llmgraph::Graph;
+/// use llmgraph::models::checkpoint::InMemoryCheckpointStore;
+/// use std::sync::Arc;
+///
+/// let mut graph = Graph::new();
+/// graph.set_checkpoint_store(Arc::new(InMemoryCheckpointStore::new()));
+/// ```
+#[async_trait]
+pub trait CheckpointStore: Send + Sync {
+    /// Store a checkpoint, replacing any earlier checkpoint of the same run.
+    async fn save(&self, checkpoint: &Checkpoint) -> LLMGraphResult<()>;
+
+    /// Load the latest checkpoint of a run, if there is one.
+    async fn load(&self, run_id: &str) -> LLMGraphResult<Option<Checkpoint>>;
+
+    /// Delete the checkpoint of a run.
+    async fn delete(&self, run_id: &str) -> LLMGraphResult<()>;
+
+    /// Check that checkpoints can be stored under `run_id`.
+    ///
+    /// Runs call this before their first step, so a run ID the store can't
+    /// use fails the run before any agent runs. The default accepts any ID.
+    fn validate_run_id(&self, _run_id: &str) -> LLMGraphResult<()> {
+        Ok(())
+    }
+}
+
+/// A checkpoint store that keeps checkpoints in memory.
+///
+/// Checkpoints survive failed runs but not the process.
+#[derive(Default)]
+pub struct InMemoryCheckpointStore {
+    checkpoints: Mutex<HashMap<String, Checkpoint>>,
+}
+
+impl InMemoryCheckpointStore {
+    /// Create a new empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for InMemoryCheckpointStore {
+    async fn save(&self, checkpoint: &Checkpoint) -> LLMGraphResult<()> {
+        self.checkpoints
+            .lock()
+            .unwrap()
+            .insert(checkpoint.run_id.clone(), checkpoint.clone());
+        Ok(())
+    }
+
+    async fn load(&self, run_id: &str) -> LLMGraphResult<Option<Checkpoint>> {
+        Ok(self.checkpoints.lock().unwrap().get(run_id).cloned())
+    }
+
+    async fn delete(&self, run_id: &str) -> LLMGraphResult<()> {
+        self.checkpoints.lock().unwrap().remove(run_id);
+        Ok(())
+    }
+}
+
+/// A checkpoint store that writes one JSON file per run into a directory.
+///
+/// Files are named `<run_id>.json` and replaced atomically on every save.
+pub struct JsonFileCheckpointStore {
+    dir: PathBuf,
+}
+
+impl JsonFileCheckpointStore {
+    /// Create a store writing into `dir`, which is created on first save
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The file holding the checkpoint of `run_id`
+    fn path(&self, run_id: &str) -> LLMGraphResult<PathBuf> {
+        self.validate_run_id(run_id)?;
+        Ok(self.dir.join(format!("{}.json", run_id)))
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for JsonFileCheckpointStore {
+    /// Run IDs name files, so only ASCII letters, digits, `-` and `_` are allowed
+    fn validate_run_id(&self, run_id: &str) -> LLMGraphResult<()> {
+        let valid = !run_id.is_empty()
+            && run_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(LLMGraphError::CheckpointError(format!(
+                "Invalid run ID for a checkpoint file: '{}'",
+                run_id
+            )));
+        }
+        Ok(())
+    }
+
+    async fn save(&self, checkpoint: &Checkpoint) -> LLMGraphResult<()> {
+        let path = self.path(&checkpoint.run_id)?;
+        let json = serde_json::to_vec_pretty(checkpoint)?;
+        let tmp = path.with_extension("json.tmp");
+
+        tokio::fs::create_dir_all(&self.dir).await.map_err(io_error)?;
+        tokio::fs::write(&tmp, json).await.map_err(io_error)?;
+        tokio::fs::rename(&tmp, &path).await.map_err(io_error)
+    }
+
+    async fn load(&self, run_id: &str) -> LLMGraphResult<Option<Checkpoint>> {
+        match tokio::fs::read(self.path(run_id)?).await {
+            Ok(json) => Ok(Some(serde_json::from_slice(&json)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error(e)),
+        }
+    }
+
+    async fn delete(&self, run_id: &str) -> LLMGraphResult<()> {
+        match tokio::fs::remove_file(self.path(run_id)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io_error(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn io_error(error: std::io::Error) -> LLMGraphError {
+    LLMGraphError::CheckpointError(error.to_string())
+}
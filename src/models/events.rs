@@ -0,0 +1,81 @@
+//! Live events emitted while a graph runs.
+//!
+//! [`Graph::run_stream`](crate::Graph::run_stream) returns a stream of
+//! [`GraphEvent`]s, so a UI can show progress while the run is in flight
+//! instead of waiting for the final result.
+
+use serde_json::Value;
+use crate::errors::LLMGraphResult;
+use crate::models::run::{RunContext, RunResult, Step};
+use crate::models::tools::{Tool, ToolRegistryTrait};
+
+/// An event emitted during a graph run.
+#[derive(Debug)]
+pub enum GraphEvent {
+    /// A node is about to run
+    NodeStarted {
+        node_id: i32,
+        agent_name: String,
+        input: String,
+    },
+    /// A node has finished; the step is the one recorded in the trace
+    NodeFinished(Step),
+    /// An agent called a tool
+    ToolCalled {
+        node_id: i32,
+        name: String,
+        arguments: String,
+    },
+    /// A tool call returned, with the error message if it failed
+    ToolResult {
+        node_id: i32,
+        name: String,
+        result: Result<Value, String>,
+    },
+    /// An agent produced part of its output (see [`RunContext::emit_token`])
+    TokenDelta {
+        node_id: i32,
+        delta: String,
+    },
+    /// The run completed or paused; always the last event of a successful run
+    RunFinished(RunResult),
+    /// The run stopped with an error; always the last event of a failed run
+    RunFailed(RunResult),
+}
+
+/// A tool registry that reports every tool call as events.
+pub(crate) struct EventToolRegistry<'a> {
+    inner: &'a dyn ToolRegistryTrait,
+    ctx: &'a RunContext,
+}
+
+impl<'a> EventToolRegistry<'a> {
+    pub(crate) fn new(inner: &'a dyn ToolRegistryTrait, ctx: &'a RunContext) -> Self {
+        Self { inner, ctx }
+    }
+}
+
+impl<'a> ToolRegistryTrait for EventToolRegistry<'a> {
+    fn get_tools(&self) -> Vec<Tool> {
+        self.inner.get_tools()
+    }
+
+    fn execute_tool(&self, name: &str, arguments: &str) -> LLMGraphResult<Value> {
+        self.ctx.emit(GraphEvent::ToolCalled {
+            node_id: self.ctx.node_id(),
+            name: name.to_string(),
+            arguments: arguments.to_string(),
+        });
+        let result = self.inner.execute_tool(name, arguments);
+        self.ctx.emit(GraphEvent::ToolResult {
+            node_id: self.ctx.node_id(),
+            name: name.to_string(),
+            result: result.as_ref().cloned().map_err(|e| e.to_string()),
+        });
+        result
+    }
+
+    fn unreported(&self) -> Option<&dyn ToolRegistryTrait> {
+        Some(self.inner)
+    }
+}
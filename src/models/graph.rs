@@ -4,7 +4,22 @@
 //! conversational AI applications with function calling capabilities.
 
 use async_trait::async_trait;
-use std::collections::HashMap;
+use futures::channel::mpsc::{self, UnboundedSender};
+use futures::future::{join_all, BoxFuture, FutureExt};
+use futures::stream::{self, Stream, StreamExt};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use crate::agents::parallel::CombineStrategy;
+use crate::errors::{AgentError, ErrorFrame, GraphError, LLMGraphError, LLMGraphResult, NodeError};
+use crate::models::checkpoint::{Checkpoint, CheckpointStore};
+use crate::models::events::{EventToolRegistry, GraphEvent};
+use crate::models::middleware::Middleware;
+use crate::models::response::{AgentAction, AgentResponse};
+use crate::models::run::{new_run_id, CancellationToken, RunContext, RunOptions, RunResult, RunStatus, Step};
 use crate::models::tools::{ToolRegistry, Tool, ToolRegistryTrait, CombinedToolRegistry};
 
 /// Trait for implementing agents that can process inputs and communicate within the graph.
@@ -51,8 +66,70 @@ pub trait Agent: Send {
         tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
     ) -> (String, Option<i32>);
     
+    /// Process input with access to the run's shared state.
+    ///
+    /// The default implementation ignores the context and calls
+    /// [`Agent::run`]; override it to read or write run state through
+    /// [`RunContext`].
+    ///
+    /// # Arguments
+    /// * `input` - The input string to process
+    /// * `tool_registry` - Registry containing available tools/functions
+    /// * `ctx` - The run context holding the shared run state
+    async fn run_with_context(
+        &mut self,
+        input: &str,
+        tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        _ctx: &RunContext,
+    ) -> (String, Option<i32>) {
+        self.run(input, tool_registry).await
+    }
+
+    /// Process input and return a typed [`AgentResponse`].
+    ///
+    /// The graph executor calls this method. The default implementation
+    /// adapts [`Agent::run_with_context`]: a route becomes
+    /// [`AgentAction::Next`] and `None` becomes [`AgentAction::End`].
+    /// Override it to report failures as [`AgentAction::Fail`] instead of
+    /// in the output text, to fork, or to attach metadata to the step.
+    ///
+    /// # Arguments
+    /// * `input` - The input string to process
+    /// * `tool_registry` - Registry containing available tools/functions
+    /// * `ctx` - The run context holding the shared run state
+    async fn execute(
+        &mut self,
+        input: &str,
+        tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ctx: &RunContext,
+    ) -> AgentResponse {
+        self.run_with_context(input, tool_registry, ctx).await.into()
+    }
+
     /// Get the name of this agent for identification purposes.
     fn get_name(&self) -> &str;
+
+    /// Serialize the agent's internal state for a checkpoint.
+    ///
+    /// Agents that keep state between invocations can return it here so a
+    /// resumed run (see [`Graph::resume`]) continues with the same state.
+    /// The default returns `None` (nothing to save).
+    fn snapshot(&self) -> Option<String> {
+        None
+    }
+
+    /// Restore internal state previously returned by [`Agent::snapshot`].
+    fn restore(&mut self, _snapshot: &str) -> LLMGraphResult<()> {
+        Ok(())
+    }
+
+    /// Node IDs this agent may route to, as declared by its configuration.
+    ///
+    /// Used by [`Graph::validate`] to catch dangling or unreachable routes before
+    /// a run. Agents that compute routes dynamically can leave the default.
+    fn declared_routes(&self) -> Vec<i32> {
+        Vec::new()
+    }
 }
 
 /// A directed graph of agents that can communicate and pass messages.
@@ -61,6 +138,11 @@ pub trait Agent: Send {
 /// - Dynamic agent registration
 /// - Tool registration (both global and node-specific)
 /// - Sequential message passing through connected agents
+/// - Directed edges, optionally enforced at run time (see [`Graph::set_strict_edges`])
+/// - Fan-out / fan-in: a fan-out node runs several branches concurrently and
+///   merges their outputs into a join node (see [`Graph::add_fan_out`])
+/// - Concurrent runs: `run` takes `&self`, so one graph (e.g. in an `Arc`) can
+///   serve several tokio tasks. Each node's agent is locked while it runs.
 ///
 /// # Example
 /// ```rust,no_run
@@ -91,13 +173,88 @@ pub trait Agent: Send {
 /// ```
 pub struct Graph {
     nodes: HashMap<i32, Node>,
-    tool_registry: ToolRegistry, // Shared tool registry
+    tool_registry: Arc<ToolRegistry>, // Shared tool registry
+    undirected_edges: HashSet<(i32, i32)>, // Links added with add_edge, as (min, max)
+    fan_outs: HashMap<i32, FanOut>,
+    strict_edges: bool,
+    max_steps: Option<usize>,
+    max_visits_per_node: Option<usize>,
+    checkpoint_store: Option<Arc<dyn CheckpointStore>>,
+    interrupts: HashMap<i32, String>, // Nodes that pause the run before they execute
+    node_timeouts: HashMap<i32, Duration>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    node_middleware: HashMap<i32, Vec<Arc<dyn Middleware>>>,
 }
 
 struct Node {
-    agent: Box<dyn Agent>,
+    agent: Mutex<Box<dyn Agent>>, // Locked for the duration of each invocation
+    name: String,                 // Cached so inspection never waits on a running agent
+    routes: Vec<i32>,             // Routes declared by the agent's configuration
     neighbors: Vec<i32>,
-    tool_registry: ToolRegistry, // Node-specific tool registry
+    tool_registry: Arc<ToolRegistry>, // Node-specific tool registry
+}
+
+/// A fan-out node's branches, where they join and how their outputs merge.
+struct FanOut {
+    branches: Vec<i32>,
+    join: i32,
+    strategy: CombineStrategy,
+}
+
+/// Placeholder agent for fan-out nodes; the executor never invokes it.
+struct FanOutAgent;
+
+#[async_trait]
+impl Agent for FanOutAgent {
+    async fn run(
+        &mut self,
+        input: &str,
+        _tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+    ) -> (String, Option<i32>) {
+        (input.to_string(), None)
+    }
+
+    fn get_name(&self) -> &str {
+        "FanOut"
+    }
+}
+
+/// The steps and final output of a chain of agents, and the error that stopped it.
+struct Chain {
+    steps: Vec<Step>,
+    output: String,
+    error: Option<LLMGraphError>,
+    agent_states: HashMap<i32, String>, // Agent snapshots for checkpoints
+    paused: Option<(i32, String)>,      // Node and prompt of the interrupt that paused the run
+}
+
+impl Chain {
+    fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            output: String::new(),
+            error: None,
+            agent_states: HashMap::new(),
+            paused: None,
+        }
+    }
+}
+
+/// Bookkeeping shared by the main chain of a run and its fan-out branches.
+struct RunScope<'p> {
+    ctx: RunContext,
+    steps_taken: AtomicUsize,
+    human_input: Option<String>, // Passed to the first node of a run resumed with resume_with
+    outer_tools: Option<&'p dyn ToolRegistryTrait>, // Set for subgraph runs, which don't checkpoint
+    deadline: Option<Instant>,
+}
+
+/// How a run is observed and stopped from outside.
+#[derive(Default)]
+struct RunControls {
+    events: Option<UnboundedSender<GraphEvent>>,
+    cancellation: Option<CancellationToken>,
+    deadline: Option<std::time::Instant>,
 }
 
 impl Graph {
@@ -112,10 +269,167 @@ impl Graph {
     pub fn new() -> Self {
         Self {
             nodes: HashMap::new(),
-            tool_registry: ToolRegistry::new(),
+            tool_registry: Arc::new(ToolRegistry::new()),
+            undirected_edges: HashSet::new(),
+            fan_outs: HashMap::new(),
+            strict_edges: false,
+            max_steps: None,
+            max_visits_per_node: None,
+            checkpoint_store: None,
+            interrupts: HashMap::new(),
+            node_timeouts: HashMap::new(),
+            middleware: Vec::new(),
+            node_middleware: HashMap::new(),
         }
     }
 
+    /// Only allow hops along declared edges.
+    ///
+    /// When enabled, [`Graph::run`] and [`Graph::try_run`] refuse to follow a
+    /// route returned by an agent unless the target is a declared neighbor of
+    /// the current node, and report the hop as [`GraphError::InvalidEdge`].
+    /// Disabled by default, in which case any existing node may be routed to.
+    ///
+    /// # Example
+    /// ```rust
+    /// use llmgraph::Graph;
+    ///
+    /// let mut graph = Graph::new();
+    /// graph.set_strict_edges(true);
+    /// ```
+    pub fn set_strict_edges(&mut self, strict: bool) {
+        self.strict_edges = strict;
+    }
+
+    /// Limit the number of agent invocations in a single run.
+    ///
+    /// A run that would exceed the budget stops with
+    /// [`AgentError::MaxIterationsExceeded`], keeping the steps executed so far.
+    /// `None` (the default) disables the limit.
+    ///
+    /// # Example
+    /// ```rust
+    /// use llmgraph::Graph;
+    ///
+    /// let mut graph = Graph::new();
+    /// graph.set_max_steps(Some(50));
+    /// ```
+    pub fn set_max_steps(&mut self, max_steps: Option<usize>) {
+        self.max_steps = max_steps;
+    }
+
+    /// Limit how often any single node may run within one run.
+    ///
+    /// A run that would visit a node more often stops with
+    /// [`GraphError::CycleDetected`], carrying the node path of the cycle and
+    /// keeping the steps executed so far. `None` (the default) disables the limit.
+    ///
+    /// # Example
+    /// ```rust
+    /// use llmgraph::Graph;
+    ///
+    /// let mut graph = Graph::new();
+    /// graph.set_max_visits_per_node(Some(3));
+    /// ```
+    pub fn set_max_visits_per_node(&mut self, max_visits: Option<usize>) {
+        self.max_visits_per_node = max_visits;
+    }
+
+    /// Write a checkpoint to `store` after every completed step.
+    ///
+    /// Checkpoints hold the next node, its pending input, the run state, the
+    /// trace and agent snapshots (see [`Agent::snapshot`]), so a run can be
+    /// continued with [`Graph::resume`]. Fan-out branches are checkpointed as
+    /// a whole once they have joined. A failing save stops the run with the
+    /// store's error.
+    ///
+    /// # Example
+    /// ```rust
+    /// use llmgraph::Graph;
+    /// use llmgraph::models::checkpoint::JsonFileCheckpointStore;
+    /// use std::sync::Arc;
+    ///
+    /// let mut graph = Graph::new();
+    /// graph.set_checkpoint_store(Arc::new(JsonFileCheckpointStore::new("checkpoints")));
+    /// ```
+    pub fn set_checkpoint_store(&mut self, store: Arc<dyn CheckpointStore>) {
+        self.checkpoint_store = Some(store);
+    }
+
+    /// Mark a node as an interrupt point that pauses the run before it executes.
+    ///
+    /// A run that reaches the node ends with [`RunStatus::Paused`] carrying
+    /// `prompt`, and writes a checkpoint. [`Graph::resume_with`] then runs the
+    /// node with its original input and the human input available through
+    /// [`RunContext::human_input`]. Agents can also pause a run themselves
+    /// with [`RunContext::interrupt`].
+    ///
+    /// Resuming requires a checkpoint store (see [`Graph::set_checkpoint_store`]);
+    /// without one, a run reaching an interrupt fails with
+    /// [`LLMGraphError::CheckpointError`] instead of pausing. Interrupts are
+    /// only supported on the main chain, not in fan-out branches.
+    ///
+    /// # Returns
+    /// * `Ok(())` if successful
+    /// * `Err(NodeError::NodeNotFound)` if the node doesn't exist
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use llmgraph::Graph;
+    /// # let mut graph = Graph::new();
+    /// graph.set_interrupt(2, "Approve publishing this draft?").unwrap();
+    /// ```
+    pub fn set_interrupt(&mut self, node_id: i32, prompt: impl Into<String>) -> LLMGraphResult<()> {
+        self.require_node(node_id)?;
+        self.interrupts.insert(node_id, prompt.into());
+        Ok(())
+    }
+
+    /// Limit how long the agent of a node may run.
+    ///
+    /// An agent that takes longer is dropped, which also aborts its in-flight
+    /// HTTP requests, and the run ends with [`NodeError::Timeout`]. The step
+    /// is still recorded in the trace, with the error. For a limit on the
+    /// whole run, see [`RunOptions::with_timeout`].
+    ///
+    /// # Returns
+    /// * `Ok(())` if successful
+    /// * `Err(NodeError::NodeNotFound)` if the node doesn't exist
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use llmgraph::Graph;
+    /// # use std::time::Duration;
+    /// # let mut graph = Graph::new();
+    /// graph.set_node_timeout(1, Duration::from_secs(30)).unwrap();
+    /// ```
+    pub fn set_node_timeout(&mut self, node_id: i32, timeout: Duration) -> LLMGraphResult<()> {
+        self.require_node(node_id)?;
+        self.node_timeouts.insert(node_id, timeout);
+        Ok(())
+    }
+
+    /// Add middleware that runs around every agent invocation.
+    ///
+    /// See [`Middleware`] for the order hooks run in. Fan-out nodes don't run
+    /// an agent, so middleware only sees their branches.
+    pub fn add_middleware(&mut self, middleware: Arc<dyn Middleware>) {
+        self.middleware.push(middleware);
+    }
+
+    /// Add middleware that only runs around the agent of one node.
+    ///
+    /// Node middleware runs inside the global middleware.
+    ///
+    /// # Returns
+    /// * `Ok(())` if successful
+    /// * `Err(NodeError::NodeNotFound)` if the node doesn't exist
+    pub fn add_node_middleware(&mut self, node_id: i32, middleware: Arc<dyn Middleware>) -> LLMGraphResult<()> {
+        self.require_node(node_id)?;
+        self.node_middleware.entry(node_id).or_default().push(middleware);
+        Ok(())
+    }
+
     /// Register a tool globally (available to all agents).
     ///
     /// # Arguments
@@ -135,7 +449,7 @@ impl Graph {
     where
         F: Fn(serde_json::Value) -> Result<serde_json::Value, String> + Send + Sync + 'static,
     {
-        self.tool_registry.register_tool(tool, function);
+        Arc::make_mut(&mut self.tool_registry).register_tool(tool, function);
     }
 
     /// Register a tool for a specific node only.
@@ -147,7 +461,7 @@ impl Graph {
     ///
     /// # Returns
     /// * `Ok(())` if successful
-    /// * `Err(String)` if the node doesn't exist
+    /// * `Err(NodeError::NodeNotFound)` if the node doesn't exist
     ///
     /// # Example
     /// ```rust,ignore
@@ -163,16 +477,13 @@ impl Graph {
         node_id: i32,
         tool: Tool,
         function: F,
-    ) -> Result<(), String>
+    ) -> LLMGraphResult<()>
     where
         F: Fn(serde_json::Value) -> Result<serde_json::Value, String> + Send + Sync + 'static,
     {
-        if let Some(node) = self.nodes.get_mut(&node_id) {
-            node.tool_registry.register_tool(tool, function);
-            Ok(())
-        } else {
-            Err(format!("Node {} does not exist", node_id))
-        }
+        let node = self.nodes.get_mut(&node_id).ok_or(NodeError::NodeNotFound(node_id))?;
+        Arc::make_mut(&mut node.tool_registry).register_tool(tool, function);
+        Ok(())
     }
 
     /// Get the tool registry for a specific node.
@@ -184,7 +495,7 @@ impl Graph {
     /// * `Some(&ToolRegistry)` if the node exists
     /// * `None` if the node doesn't exist
     pub fn get_node_tool_registry(&self, node_id: i32) -> Option<&ToolRegistry> {
-        self.nodes.get(&node_id).map(|node| &node.tool_registry)
+        self.nodes.get(&node_id).map(|node| node.tool_registry.as_ref())
     }
 
     /// Get the shared global tool registry.
@@ -192,7 +503,7 @@ impl Graph {
     /// # Returns
     /// A reference to the global tool registry
     pub fn get_shared_tool_registry(&self) -> &ToolRegistry {
-        &self.tool_registry
+        self.tool_registry.as_ref()
     }
 
     /// Add a new agent node to the graph.
@@ -209,16 +520,75 @@ impl Graph {
     /// graph.add_node(0, Box::new(my_agent));
     /// ```
     pub fn add_node(&mut self, id: i32, agent: Box<dyn Agent>) {
+        self.fan_outs.remove(&id);
         self.nodes.insert(
             id,
             Node {
-                agent,
+                name: agent.get_name().to_string(),
+                routes: agent.declared_routes(),
+                agent: Mutex::new(agent),
                 neighbors: Vec::new(),
-                tool_registry: ToolRegistry::new(),
+                tool_registry: Arc::new(ToolRegistry::new()),
             },
         );
     }
 
+    /// Add a fan-out node that runs several branches concurrently and joins them.
+    ///
+    /// When a run reaches `id` (as the start node or because an agent routed
+    /// to it), its input is sent to every branch at once. Each branch runs
+    /// through the graph until it routes to `join` or ends. The branch outputs
+    /// are merged with `strategy`, in branch order, and passed to `join` as input.
+    ///
+    /// Branch nodes are ordinary graph nodes, so they use their own node tools
+    /// and appear in [`Graph::print`]. Branch steps are recorded in the trace
+    /// in branch order, followed by a `FanOut` step holding the merged output.
+    ///
+    /// # Arguments
+    /// * `id` - The ID of the fan-out node
+    /// * `branches` - The nodes each branch starts at
+    /// * `join` - The node that receives the merged output
+    /// * `strategy` - How to merge the branch outputs
+    ///
+    /// # Returns
+    /// * `Ok(())` if successful
+    /// * `Err(GraphError::EmptyFanOut)` if there are no branches
+    /// * `Err(NodeError::NodeNotFound)` if a branch or join node doesn't exist
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use llmgraph::Graph;
+    /// # use llmgraph::agents::parallel::CombineStrategy;
+    /// # let mut graph = Graph::new();
+    /// // Research (1) and Pricing (2) run concurrently, Writer (3) gets both results
+    /// graph.add_fan_out(10, vec![1, 2], 3, CombineStrategy::JsonArray).unwrap();
+    /// graph.add_edge(0, 10).unwrap();
+    /// ```
+    pub fn add_fan_out(
+        &mut self,
+        id: i32,
+        branches: Vec<i32>,
+        join: i32,
+        strategy: CombineStrategy,
+    ) -> LLMGraphResult<()> {
+        if branches.is_empty() {
+            return Err(GraphError::EmptyFanOut(id).into());
+        }
+        for &node_id in branches.iter().chain([&join]) {
+            self.require_node(node_id)?;
+        }
+
+        self.add_node(id, Box::new(FanOutAgent));
+        for &branch in &branches {
+            self.add_directed_edge(id, branch)?;
+        }
+        if let Some(node) = self.nodes.get_mut(&id) {
+            node.routes = branches.iter().copied().chain([join]).collect();
+        }
+        self.fan_outs.insert(id, FanOut { branches, join, strategy });
+        Ok(())
+    }
+
     /// Add a bidirectional edge between two nodes.
     ///
     /// # Arguments
@@ -227,7 +597,7 @@ impl Graph {
     ///
     /// # Returns
     /// * `Ok(())` if successful
-    /// * `Err(String)` if one or both nodes don't exist
+    /// * `Err(NodeError::NodeNotFound)` if one or both nodes don't exist
     ///
     /// # Example
     /// ```rust,ignore
@@ -236,24 +606,192 @@ impl Graph {
     /// # // Assuming nodes 0 and 1 exist
     /// graph.add_edge(0, 1).unwrap();
     /// ```
-    pub fn add_edge(&mut self, u: i32, v: i32) -> Result<(), String> {
-        if !self.nodes.contains_key(&u) || !self.nodes.contains_key(&v) {
-            return Err("One or both nodes do not exist".to_string());
+    pub fn add_edge(&mut self, u: i32, v: i32) -> LLMGraphResult<()> {
+        self.add_directed_edge(u, v)?;
+        self.add_directed_edge(v, u)?;
+        self.undirected_edges.insert((u.min(v), u.max(v)));
+        Ok(())
+    }
+
+    /// Add a directed edge, allowing `from` to hand off to `to` but not back.
+    ///
+    /// # Arguments
+    /// * `from` - The node that routes
+    /// * `to` - The node that may be routed to
+    ///
+    /// # Returns
+    /// * `Ok(())` if successful
+    /// * `Err(NodeError::NodeNotFound)` if one or both nodes don't exist
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use llmgraph::Graph;
+    /// # let mut graph = Graph::new();
+    /// # // Assuming nodes 0 and 1 exist
+    /// graph.add_directed_edge(0, 1).unwrap(); // 0 -> 1 only
+    /// ```
+    pub fn add_directed_edge(&mut self, from: i32, to: i32) -> LLMGraphResult<()> {
+        self.require_node(from)?;
+        self.require_node(to)?;
+
+        if let Some(node) = self.nodes.get_mut(&from) {
+            if !node.neighbors.contains(&to) {
+                node.neighbors.push(to);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fail with [`NodeError::NodeNotFound`] unless `node_id` exists.
+    fn require_node(&self, node_id: i32) -> LLMGraphResult<()> {
+        if self.nodes.contains_key(&node_id) {
+            Ok(())
+        } else {
+            Err(NodeError::NodeNotFound(node_id).into())
+        }
+    }
+
+    /// Check whether `to` is a declared neighbor of `from`.
+    pub fn has_edge(&self, from: i32, to: i32) -> bool {
+        self.nodes
+            .get(&from)
+            .map(|node| node.neighbors.contains(&to))
+            .unwrap_or(false)
+    }
+
+    /// Check the graph structure before running it from `start_id`.
+    ///
+    /// Reports, in order:
+    /// * [`GraphError::EmptyGraph`] if the graph has no nodes
+    /// * [`NodeError::NodeNotFound`] if the start node doesn't exist
+    /// * [`GraphError::InvalidEdge`] for every route declared by an agent
+    ///   (see [`Agent::declared_routes`]) that targets a missing node, or that
+    ///   is not a declared edge while strict edges are enabled
+    /// * [`GraphError::DisconnectedGraph`] listing nodes unreachable from the start
+    /// * [`GraphError::CycleDetected`] for every cycle, unless a step budget or
+    ///   visit limit guards the run. Links added with [`Graph::add_edge`] are
+    ///   bidirectional by design and don't count as cycles on their own.
+    ///
+    /// # Returns
+    /// * `Ok(())` if no problems were found
+    /// * `Err(Vec<LLMGraphError>)` with every problem found
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use llmgraph::Graph;
+    /// # let graph = Graph::new();
+    /// if let Err(problems) = graph.validate(0) {
+    ///     for problem in problems {
+    ///         eprintln!("{}", problem);
+    ///     }
+    /// }
+    /// ```
+    pub fn validate(&self, start_id: i32) -> Result<(), Vec<LLMGraphError>> {
+        if self.nodes.is_empty() {
+            return Err(vec![GraphError::EmptyGraph.into()]);
+        }
+        if !self.nodes.contains_key(&start_id) {
+            return Err(vec![NodeError::NodeNotFound(start_id).into()]);
         }
 
-        if let Some(node) = self.nodes.get_mut(&u) {
-            if !node.neighbors.contains(&v) {
-                node.neighbors.push(v);
+        let mut problems: Vec<LLMGraphError> = Vec::new();
+        let mut ids: Vec<i32> = self.nodes.keys().copied().collect();
+        ids.sort();
+
+        // Dangling route targets
+        for id in &ids {
+            for &target in &self.nodes[id].routes {
+                let dangling = !self.nodes.contains_key(&target)
+                    || (self.strict_edges && !self.has_edge(*id, target));
+                if dangling {
+                    problems.push(GraphError::InvalidEdge { from: *id, to: target }.into());
+                }
             }
         }
 
-        if let Some(node) = self.nodes.get_mut(&v) {
-            if !node.neighbors.contains(&u) {
-                node.neighbors.push(u);
+        // Reachability over edges and declared routes
+        let mut reached = HashSet::from([start_id]);
+        let mut queue = VecDeque::from([start_id]);
+        while let Some(id) = queue.pop_front() {
+            let node = &self.nodes[&id];
+            for &next in node.neighbors.iter().chain(&node.routes) {
+                if self.nodes.contains_key(&next) && reached.insert(next) {
+                    queue.push_back(next);
+                }
             }
         }
+        let unreachable: Vec<i32> = ids.iter().copied().filter(|id| !reached.contains(id)).collect();
+        if !unreachable.is_empty() {
+            problems.push(GraphError::DisconnectedGraph(unreachable).into());
+        }
 
-        Ok(())
+        // Cycles, unless the run is guarded
+        if self.max_steps.is_none() && self.max_visits_per_node.is_none() {
+            for cycle in self.find_cycles(&ids) {
+                problems.push(GraphError::CycleDetected(cycle).into());
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Hops a run may take from `id` after arriving from `parent`: edges and
+    /// declared routes, minus the bounce straight back over an undirected link.
+    fn successors(&self, id: i32, parent: Option<i32>) -> Vec<i32> {
+        let node = &self.nodes[&id];
+        let bounce = parent.filter(|&p| {
+            self.undirected_edges.contains(&(id.min(p), id.max(p))) && !node.routes.contains(&p)
+        });
+        let mut successors: Vec<i32> = node
+            .neighbors
+            .iter()
+            .chain(&node.routes)
+            .copied()
+            .filter(|next| self.nodes.contains_key(next) && Some(*next) != bounce)
+            .collect();
+        successors.sort();
+        successors.dedup();
+        successors
+    }
+
+    /// Find cycles with a depth-first search, one per back edge.
+    fn find_cycles(&self, ids: &[i32]) -> Vec<Vec<i32>> {
+        let mut cycles = Vec::new();
+        let mut done: HashSet<i32> = HashSet::new();
+
+        for &root in ids {
+            if done.contains(&root) {
+                continue;
+            }
+            // Stack of (node, successors, next successor index)
+            let mut path: Vec<i32> = vec![root];
+            let mut stack = vec![(root, self.successors(root, None), 0)];
+            while let Some((id, successors, index)) = stack.last_mut() {
+                if let Some(&next) = successors.get(*index) {
+                    *index += 1;
+                    if let Some(position) = path.iter().position(|&p| p == next) {
+                        let mut cycle = path[position..].to_vec();
+                        cycle.push(next);
+                        cycles.push(cycle);
+                    } else if !done.contains(&next) {
+                        let successors = self.successors(next, Some(*id));
+                        path.push(next);
+                        stack.push((next, successors, 0));
+                    }
+                } else {
+                    done.insert(*id);
+                    path.pop();
+                    stack.pop();
+                }
+            }
+        }
+
+        cycles
     }
 
     /// Print the graph structure to stdout for debugging.
@@ -270,8 +808,9 @@ impl Graph {
     /// ```
     pub fn print(&self) {
         println!("Adjacency list for the Graph:");
-        for (id, node) in &self.nodes {
-            print!("{} (Agent: {}) -> ", id, node.agent.get_name());
+        for id in self.sorted_node_ids() {
+            let node = &self.nodes[&id];
+            print!("{} (Agent: {}) -> ", id, node.name);
             for neighbor in &node.neighbors {
                 print!("{} ", neighbor);
             }
@@ -279,6 +818,183 @@ impl Graph {
         }
     }
 
+    /// Render the graph as Graphviz DOT.
+    ///
+    /// Nodes are labelled with their agent name and node-specific tools,
+    /// links added with [`Graph::add_edge`] are drawn once with arrows on both
+    /// ends, and fan-outs are drawn as diamonds with a dashed edge to their
+    /// join node. The output is sorted, so it is stable across calls.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use llmgraph::Graph;
+    /// # let graph = Graph::new();
+    /// std::fs::write("graph.dot", graph.to_dot()).unwrap();
+    /// // dot -Tsvg graph.dot -o graph.svg
+    /// ```
+    pub fn to_dot(&self) -> String {
+        self.render_dot(&TraceOverlay::default())
+    }
+
+    /// Render the graph as Graphviz DOT with a run trace overlaid.
+    ///
+    /// Visited nodes show their visit count and total latency, and edges show
+    /// how often they were taken. Hops without a declared edge are dotted.
+    pub fn to_dot_with_trace(&self, steps: &[Step]) -> String {
+        self.render_dot(&TraceOverlay::new(steps))
+    }
+
+    /// Render the graph as a Mermaid flowchart, e.g. for Markdown docs.
+    ///
+    /// Uses the same conventions as [`Graph::to_dot`].
+    pub fn to_mermaid(&self) -> String {
+        self.render_mermaid(&TraceOverlay::default())
+    }
+
+    /// Render the graph as a Mermaid flowchart with a run trace overlaid.
+    ///
+    /// Uses the same conventions as [`Graph::to_dot_with_trace`].
+    pub fn to_mermaid_with_trace(&self, steps: &[Step]) -> String {
+        self.render_mermaid(&TraceOverlay::new(steps))
+    }
+
+    fn render_dot(&self, trace: &TraceOverlay) -> String {
+        let mut dot = String::from("digraph llmgraph {\n    rankdir=LR;\n    node [shape=box];\n");
+        for id in self.sorted_node_ids() {
+            let label = self.node_label(id, trace, "\n").replace('\\', "\\\\").replace('"', "\\\"");
+            let mut attributes = vec![format!("label=\"{}\"", label.replace('\n', "\\n"))];
+            if self.fan_outs.contains_key(&id) {
+                attributes.push("shape=diamond".to_string());
+            }
+            if trace.visits.contains_key(&id) {
+                attributes.push("style=bold".to_string());
+            }
+            dot.push_str(&format!("    {} [{}];\n", id, attributes.join(", ")));
+        }
+        for edge in self.render_edges(trace) {
+            let mut attributes = Vec::new();
+            if edge.both_ways {
+                attributes.push("dir=both".to_string());
+            }
+            match edge.kind {
+                EdgeKind::Declared => {}
+                EdgeKind::Join => attributes.push("style=dashed".to_string()),
+                EdgeKind::Undeclared => attributes.push("style=dotted".to_string()),
+            }
+            if let Some(label) = edge.label() {
+                attributes.push(format!("label=\"{}\"", label));
+            }
+            if edge.taken > 0 {
+                attributes.push("penwidth=2".to_string());
+            }
+            if attributes.is_empty() {
+                dot.push_str(&format!("    {} -> {};\n", edge.from, edge.to));
+            } else {
+                dot.push_str(&format!("    {} -> {} [{}];\n", edge.from, edge.to, attributes.join(", ")));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn render_mermaid(&self, trace: &TraceOverlay) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+        for id in self.sorted_node_ids() {
+            let label = self.node_label(id, trace, "<br/>").replace('"', "#quot;");
+            if self.fan_outs.contains_key(&id) {
+                mermaid.push_str(&format!("    n{}{{\"{}\"}}\n", id, label));
+            } else {
+                mermaid.push_str(&format!("    n{}[\"{}\"]\n", id, label));
+            }
+        }
+        for edge in self.render_edges(trace) {
+            let arrow = match (edge.kind, edge.both_ways) {
+                (EdgeKind::Declared, true) => "<-->",
+                (EdgeKind::Declared, false) => "-->",
+                (_, true) => "<-.->",
+                (_, false) => "-.->",
+            };
+            let label = edge.label().map(|label| format!("|{}|", label)).unwrap_or_default();
+            mermaid.push_str(&format!("    n{} {}{} n{}\n", edge.from, arrow, label, edge.to));
+        }
+        let mut visited: Vec<i32> = trace.visits.keys().copied().collect();
+        if !visited.is_empty() {
+            visited.sort_unstable();
+            let visited: Vec<String> = visited.iter().map(|id| format!("n{}", id)).collect();
+            mermaid.push_str("    classDef visited stroke-width:3px\n");
+            mermaid.push_str(&format!("    class {} visited\n", visited.join(",")));
+        }
+        mermaid
+    }
+
+    fn sorted_node_ids(&self) -> Vec<i32> {
+        let mut ids: Vec<i32> = self.nodes.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// The label of a node: ID, agent name, node-specific tools and trace stats
+    fn node_label(&self, id: i32, trace: &TraceOverlay, line_break: &str) -> String {
+        let node = &self.nodes[&id];
+        let mut label = format!("{}: {}", id, node.name);
+        let mut tools: Vec<String> = node
+            .tool_registry
+            .get_tools()
+            .into_iter()
+            .map(|tool| tool.function.name)
+            .collect();
+        if !tools.is_empty() {
+            tools.sort();
+            label.push_str(&format!("{}tools: {}", line_break, tools.join(", ")));
+        }
+        if let Some((visits, latency)) = trace.visits.get(&id) {
+            let visits = if *visits == 1 { "1 visit".to_string() } else { format!("{} visits", visits) };
+            label.push_str(&format!("{}{}, {} ms", line_break, visits, latency.as_millis()));
+        }
+        label
+    }
+
+    /// All edges to render, sorted, with links in both directions merged
+    fn render_edges(&self, trace: &TraceOverlay) -> Vec<RenderedEdge> {
+        let mut edges = Vec::new();
+        let mut rendered = HashSet::new();
+        for id in self.sorted_node_ids() {
+            let node = &self.nodes[&id];
+            let mut neighbors = node.neighbors.clone();
+            neighbors.sort_unstable();
+            for to in neighbors {
+                let both_ways = self.undirected_edges.contains(&(id.min(to), id.max(to)));
+                if both_ways && to < id {
+                    continue;
+                }
+                let taken = trace.hop(id, to) + if both_ways { trace.hop(to, id) } else { 0 };
+                edges.push(RenderedEdge { from: id, to, both_ways, kind: EdgeKind::Declared, taken });
+                rendered.insert((id, to));
+                if both_ways {
+                    rendered.insert((to, id));
+                }
+            }
+            if let Some(fan_out) = self.fan_outs.get(&id) {
+                let to = fan_out.join;
+                let taken = trace.hop(id, to);
+                edges.push(RenderedEdge { from: id, to, both_ways: false, kind: EdgeKind::Join, taken });
+                rendered.insert((id, to));
+            }
+        }
+
+        // Hops the trace took without a declared edge (allowed unless edges are strict)
+        let mut undeclared: Vec<(&(i32, i32), &usize)> = trace
+            .hops
+            .iter()
+            .filter(|(hop, _)| !rendered.contains(*hop) && self.nodes.contains_key(&hop.0) && self.nodes.contains_key(&hop.1))
+            .collect();
+        undeclared.sort();
+        for (&(from, to), &taken) in undeclared {
+            edges.push(RenderedEdge { from, to, both_ways: false, kind: EdgeKind::Undeclared, taken });
+        }
+        edges
+    }
+
     /// Execute the graph starting from a specific node.
     ///
     /// This method will:
@@ -286,6 +1002,9 @@ impl Graph {
     /// 2. Pass the output of each agent to the next one
     /// 3. Continue until an agent returns None for the next node
     ///
+    /// Errors (missing nodes, rejected hops) are appended to the output as
+    /// `Error: ...` lines. Use [`Graph::try_run`] to get them as typed errors.
+    ///
     /// # Arguments
     /// * `start_id` - The ID of the starting node
     /// * `input` - The initial input string
@@ -301,61 +1020,899 @@ impl Graph {
     /// let result = graph.run(0, "Process this task").await;
     /// println!("Result: {}", result);
     /// ```
-    pub async fn run(&mut self, start_id: i32, input: &str) -> String {
-        let mut current_id = start_id;
-        let mut current_input = input.to_string();
-        let mut result = String::new();
-
-        loop {
-            // First, check if the current node exists
-            if !self.nodes.contains_key(&current_id) {
-                result.push_str(&format!("Error: Node {} does not exist\n", current_id));
-                break;
+    pub async fn run(&self, start_id: i32, input: &str) -> String {
+        let run = self.run_traced(start_id, input).await;
+        let mut result = run.transcript();
+        if let Some(e) = run.error {
+            match run.error_context {
+                Some(context) => result.push_str(&format!("Error: {} (at {})\n", e, context)),
+                None => result.push_str(&format!("Error: {}\n", e)),
             }
+        }
+        result
+    }
 
-            // Use unsafe to work around the borrowing issue
-            // This is safe because we're not modifying the structure of the HashMap,
-            // only calling a method on one of its values
-            let (output, next_id) = unsafe {
-                // Get raw pointers to avoid borrowing conflicts
-                let nodes_ptr = &mut self.nodes as *mut HashMap<i32, Node>;
-                let tool_registry_ptr = &self.tool_registry as *const ToolRegistry;
-                
-                // Get the node's tool registry reference
-                let node_tool_registry_ptr = {
-                    let nodes_ref = &*nodes_ptr;
-                    &nodes_ref[&current_id].tool_registry as *const ToolRegistry
-                };
-                
-                // Create combined registry using the raw pointers
+    /// Execute the graph like [`Graph::run`], but return failures as errors.
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The accumulated output from all agents
+    /// * `Err(LLMGraphError)` - `NodeError::NodeNotFound` for a missing node, or
+    ///   `GraphError::InvalidEdge` for an undeclared hop when strict edges are enabled.
+    ///   Use [`Graph::run_traced`] to also get where the error happened
+    pub async fn try_run(&self, start_id: i32, input: &str) -> LLMGraphResult<String> {
+        let run = self.run_traced(start_id, input).await;
+        match run.error {
+            Some(e) => Err(e),
+            None => Ok(run.transcript()),
+        }
+    }
+
+    /// Execute the graph and return a structured result with a per-step trace.
+    ///
+    /// Each agent invocation is recorded as a [`Step`] with its node ID,
+    /// agent name, input, output, chosen next node, timestamps and error.
+    /// The trace is kept even when the run stops with an error.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use llmgraph::Graph;
+    /// # let mut graph = Graph::new();
+    /// let result = graph.run_traced(0, "Process this task").await;
+    /// assert!(result.is_success());
+    /// println!("{} steps, final output: {}", result.steps.len(), result.output);
+    /// ```
+    pub async fn run_traced(&self, start_id: i32, input: &str) -> RunResult {
+        self.run_with_options(start_id, input, RunOptions::new()).await
+    }
+
+    /// Execute the graph with options such as an initial run state.
+    ///
+    /// Every agent can read and write the run state through the
+    /// [`RunContext`] passed to [`Agent::run_with_context`]; the final
+    /// state is returned in [`RunResult::state`].
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use llmgraph::Graph;
+    /// # use llmgraph::models::run::RunOptions;
+    /// # let graph = Graph::new();
+    /// let result = graph.run_with_options(0, "New ticket", RunOptions::new()).await;
+    /// println!("Priority: {:?}", result.state.get("priority"));
+    /// ```
+    pub async fn run_with_options(&self, start_id: i32, input: &str, options: RunOptions) -> RunResult {
+        self.start_run(start_id, input, options, None).await
+    }
+
+    /// Execute the graph and stream [`GraphEvent`]s while it runs.
+    ///
+    /// The stream yields `NodeStarted`/`NodeFinished` around every step,
+    /// `ToolCalled`/`ToolResult` around every tool call, `TokenDelta` for
+    /// output reported through [`RunContext::emit_token`], and ends with
+    /// `RunFinished` or `RunFailed` carrying the [`RunResult`]. The run only
+    /// progresses while the stream is polled.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use llmgraph::{Graph, RunOptions};
+    /// # use llmgraph::models::events::GraphEvent;
+    /// use futures::StreamExt;
+    /// # let graph = Graph::new();
+    /// let mut events = Box::pin(graph.run_stream(0, "Process this task", RunOptions::new()));
+    /// while let Some(event) = events.next().await {
+    ///     match event {
+    ///         GraphEvent::NodeStarted { agent_name, .. } => println!("{} started", agent_name),
+    ///         GraphEvent::RunFinished(result) => println!("Done: {}", result.output),
+    ///         _ => {}
+    ///     }
+    /// }
+    /// ```
+    pub fn run_stream<'a>(
+        &'a self,
+        start_id: i32,
+        input: &str,
+        options: RunOptions,
+    ) -> impl Stream<Item = GraphEvent> + Send + 'a {
+        let input = input.to_string();
+        event_stream(move |sender| async move {
+            self.start_run(start_id, &input, options, Some(sender)).await
+        })
+    }
+
+    /// Start a new run, optionally sending its events to `events`.
+    async fn start_run(
+        &self,
+        start_id: i32,
+        input: &str,
+        options: RunOptions,
+        events: Option<UnboundedSender<GraphEvent>>,
+    ) -> RunResult {
+        let checkpoint = Checkpoint {
+            run_id: options.run_id.unwrap_or_else(new_run_id),
+            next_node: Some(start_id),
+            pending_input: input.to_string(),
+            state: options.state,
+            steps: Vec::new(),
+            agent_states: HashMap::new(),
+            interrupt: None,
+        };
+        let controls = RunControls {
+            events,
+            cancellation: options.cancellation,
+            deadline: options.deadline,
+        };
+        self.continue_run(start_id, checkpoint, None, controls).await
+    }
+
+    /// Load the checkpoint of a run to resume and restore its agent snapshots.
+    ///
+    /// The run must not have finished, and must be paused when resuming with
+    /// human input; these are checked before any snapshot is restored.
+    ///
+    /// # Returns
+    /// The node to continue at and the checkpoint
+    async fn checkpoint_to_resume(&self, run_id: &str, human_input: Option<&str>) -> LLMGraphResult<(i32, Checkpoint)> {
+        let checkpoint = self.load_checkpoint(run_id).await?;
+        let Some(next_node) = checkpoint.next_node else {
+            return Err(LLMGraphError::CheckpointError(format!(
+                "Run '{}' has already finished",
+                run_id
+            )));
+        };
+        if human_input.is_some() && checkpoint.interrupt.is_none() {
+            return Err(LLMGraphError::CheckpointError(format!(
+                "Run '{}' is not paused",
+                run_id
+            )));
+        }
+
+        for (id, snapshot) in &checkpoint.agent_states {
+            if let Some(node) = self.nodes.get(id) {
+                node.agent.lock().await.restore(snapshot)?;
+            }
+        }
+        Ok((next_node, checkpoint))
+    }
+
+    /// Continue a run from its last checkpoint.
+    ///
+    /// Agent snapshots stored in the checkpoint are restored before the
+    /// pending node runs.
+    ///
+    /// # Returns
+    /// * `Ok(RunResult)` - The result of the continued run, including the steps
+    ///   completed before the checkpoint
+    /// * `Err(LLMGraphError)` - If no checkpoint store is configured, the run has
+    ///   no checkpoint or has already finished, or an agent snapshot can't be
+    ///   restored
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use llmgraph::Graph;
+    /// # let graph = Graph::new();
+    /// let result = graph.resume("run-1700000000000-0").await?;
+    /// println!("Final output: {}", result.output);
+    /// ```
+    pub async fn resume(&self, run_id: &str) -> LLMGraphResult<RunResult> {
+        self.resume_with_options(run_id, None, RunOptions::new()).await
+    }
+
+    /// Continue a paused run with human input.
+    ///
+    /// The node the run is paused at runs again with its original input; the
+    /// human input is available to it through [`RunContext::human_input`].
+    ///
+    /// # Returns
+    /// * `Ok(RunResult)` - The result of the continued run
+    /// * `Err(LLMGraphError)` - If the run can't be loaded (see [`Graph::resume`])
+    ///   or isn't paused
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use llmgraph::{Graph, models::run::RunStatus};
+    /// # let graph = Graph::new();
+    /// let result = graph.run_traced(0, "Draft a press release").await;
+    /// if let RunStatus::Paused { run_id, prompt, .. } = &result.status {
+    ///     println!("{}", prompt);
+    ///     let result = graph.resume_with(run_id, "approved").await?;
+    /// }
+    /// ```
+    pub async fn resume_with(&self, run_id: &str, human_input: &str) -> LLMGraphResult<RunResult> {
+        self.resume_with_options(run_id, Some(human_input), RunOptions::new()).await
+    }
+
+    /// Continue a run like [`Graph::resume`] or, with `human_input`, like
+    /// [`Graph::resume_with`], under the cancellation token and deadline of
+    /// `options`.
+    ///
+    /// The run ID and state come from the checkpoint, so `options.run_id`
+    /// and `options.state` are ignored.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use llmgraph::{Graph, RunOptions};
+    /// # use std::time::Duration;
+    /// # let graph = Graph::new();
+    /// let options = RunOptions::new().with_timeout(Duration::from_secs(60));
+    /// let result = graph.resume_with_options("run-1700000000000-0", Some("approved"), options).await?;
+    /// ```
+    pub async fn resume_with_options(
+        &self,
+        run_id: &str,
+        human_input: Option<&str>,
+        options: RunOptions,
+    ) -> LLMGraphResult<RunResult> {
+        let (next_node, checkpoint) = self.checkpoint_to_resume(run_id, human_input).await?;
+        let controls = RunControls {
+            events: None,
+            cancellation: options.cancellation,
+            deadline: options.deadline,
+        };
+        Ok(self
+            .continue_run(next_node, checkpoint, human_input.map(str::to_string), controls)
+            .await)
+    }
+
+    /// Continue a run like [`Graph::resume_with_options`] and stream its
+    /// [`GraphEvent`]s like [`Graph::run_stream`].
+    ///
+    /// # Returns
+    /// * `Ok(stream)` - The events of the continued run, ending with
+    ///   `RunFinished` or `RunFailed`
+    /// * `Err(LLMGraphError)` - If the run can't be resumed (see [`Graph::resume_with_options`])
+    pub async fn resume_stream<'a>(
+        &'a self,
+        run_id: &str,
+        human_input: Option<&str>,
+        options: RunOptions,
+    ) -> LLMGraphResult<impl Stream<Item = GraphEvent> + Send + 'a> {
+        let (next_node, checkpoint) = self.checkpoint_to_resume(run_id, human_input).await?;
+        let human_input = human_input.map(str::to_string);
+        Ok(event_stream(move |sender| {
+            let controls = RunControls {
+                events: Some(sender),
+                cancellation: options.cancellation,
+                deadline: options.deadline,
+            };
+            self.continue_run(next_node, checkpoint, human_input, controls)
+        }))
+    }
+
+    /// Load the checkpoint of a run from the checkpoint store.
+    async fn load_checkpoint(&self, run_id: &str) -> LLMGraphResult<Checkpoint> {
+        let store = self.checkpoint_store.as_ref().ok_or_else(|| {
+            LLMGraphError::CheckpointError("No checkpoint store configured".to_string())
+        })?;
+        let checkpoint = store.load(run_id).await?.ok_or_else(|| {
+            LLMGraphError::CheckpointError(format!("No checkpoint for run '{}'", run_id))
+        })?;
+        Ok(checkpoint)
+    }
+
+    /// Run from `start_id`, the pending node of `checkpoint`, until the run
+    /// ends or pauses.
+    async fn continue_run(
+        &self,
+        start_id: i32,
+        checkpoint: Checkpoint,
+        human_input: Option<String>,
+        controls: RunControls,
+    ) -> RunResult {
+        let ctx = RunContext::new(start_id, checkpoint.state)
+            .with_run_id(checkpoint.run_id.clone())
+            .with_events(controls.events)
+            .with_cancellation(controls.cancellation);
+        let scope = RunScope {
+            ctx,
+            steps_taken: AtomicUsize::new(checkpoint.steps.len()),
+            human_input,
+            outer_tools: None,
+            deadline: controls.deadline.map(Instant::from_std),
+        };
+        let chain = Chain {
+            output: checkpoint.steps.last().map(|step| step.output.clone()).unwrap_or_default(),
+            steps: checkpoint.steps,
+            error: None,
+            agent_states: checkpoint.agent_states,
+            paused: None,
+        };
+
+        // Fail before the first step if checkpoints can't be stored for this run
+        let run_id_check = match &self.checkpoint_store {
+            Some(store) => store.validate_run_id(&checkpoint.run_id),
+            None => Ok(()),
+        };
+        let chain = match run_id_check {
+            Err(error) => Chain { error: Some(error), ..chain },
+            Ok(()) => self.run_chain(start_id, checkpoint.pending_input, None, &scope, chain).await,
+        };
+        finish_run(&scope, chain)
+    }
+
+    /// Run this graph as a node of an outer run (see [`SubgraphAgent`](crate::agents::SubgraphAgent)).
+    ///
+    /// The nested run shares the outer run's ID, state and event stream. Its
+    /// nodes fall back to `outer_tools` for tools the inner graph doesn't
+    /// provide, and it never writes checkpoints.
+    pub(crate) async fn run_nested(
+        &self,
+        start_id: i32,
+        input: &str,
+        outer_tools: &dyn ToolRegistryTrait,
+        ctx: &RunContext,
+    ) -> RunResult {
+        let scope = RunScope {
+            ctx: ctx.for_node(start_id),
+            steps_taken: AtomicUsize::new(0),
+            human_input: None,
+            // The inner nodes report calls to outer tools themselves
+            outer_tools: Some(outer_tools.unreported().unwrap_or(outer_tools)),
+            deadline: None, // The outer run's deadline covers the whole subgraph
+        };
+        let chain = self.run_chain(start_id, input.to_string(), None, &scope, Chain::new()).await;
+        finish_run(&scope, chain)
+    }
+
+    /// Run the agent of `node_id` inside the global and the node's middleware.
+    async fn invoke_agent(
+        &self,
+        node_id: i32,
+        agent: &mut dyn Agent,
+        input: &mut String,
+        tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ctx: &RunContext,
+    ) -> LLMGraphResult<AgentResponse> {
+        let middleware: Vec<&Arc<dyn Middleware>> = self
+            .middleware
+            .iter()
+            .chain(self.node_middleware.get(&node_id).into_iter().flatten())
+            .collect();
+
+        for hooks in &middleware {
+            hooks.before(node_id, input, ctx).await?;
+        }
+        let mut response = agent.execute(input, tool_registry, ctx).await;
+
+        // A hook that changes the route replaces a `Next` or `End`; failures,
+        // interrupts and forks keep their action
+        let original_next = response.next_id();
+        let mut next_id = original_next;
+        for hooks in middleware.iter().rev() {
+            hooks.after(node_id, &mut response.output, &mut next_id, ctx).await?;
+        }
+        let routed = matches!(response.action, AgentAction::Next(_) | AgentAction::End);
+        if routed && next_id != original_next {
+            response.action = match next_id {
+                Some(next) => AgentAction::Next(next),
+                None => AgentAction::End,
+            };
+        }
+        Ok(response)
+    }
+
+    /// Write a checkpoint for the main chain, if a store is configured.
+    ///
+    /// Pausing at an `interrupt` fails without a store, as the run could
+    /// never be resumed.
+    async fn save_checkpoint(
+        &self,
+        scope: &RunScope<'_>,
+        chain: &Chain,
+        next_node: Option<i32>,
+        pending_input: &str,
+        interrupt: Option<&str>,
+    ) -> LLMGraphResult<()> {
+        if scope.outer_tools.is_some() {
+            return Ok(());
+        }
+        let Some(store) = &self.checkpoint_store else {
+            return match (interrupt, next_node) {
+                (Some(_), Some(node)) => Err(LLMGraphError::CheckpointError(format!(
+                    "Node {} requested an interrupt, but no checkpoint store is set to resume the run from",
+                    node
+                ))),
+                _ => Ok(()),
+            };
+        };
+        let checkpoint = Checkpoint {
+            run_id: scope.ctx.run_id().to_string(),
+            next_node,
+            pending_input: pending_input.to_string(),
+            state: scope.ctx.state(),
+            steps: chain.steps.clone(),
+            agent_states: chain.agent_states.clone(),
+            interrupt: interrupt.map(str::to_string),
+        };
+        store.save(&checkpoint).await
+    }
+
+    /// Run agents from `start_id` until one ends the chain, an error occurs,
+    /// or the chain reaches `join` (used for fan-out branches).
+    ///
+    /// `chain` holds the steps already completed, e.g. when resuming. Only
+    /// the main chain (without `join`) writes checkpoints.
+    fn run_chain<'a>(
+        &'a self,
+        start_id: i32,
+        input: String,
+        join: Option<i32>,
+        scope: &'a RunScope<'a>,
+        mut chain: Chain,
+    ) -> BoxFuture<'a, Chain> {
+        async move {
+            let mut current_id = start_id;
+            let mut current_input = input;
+            let mut visits: HashMap<i32, usize> = HashMap::new();
+            for step in &chain.steps {
+                *visits.entry(step.node_id).or_insert(0) += 1;
+            }
+            let mut human_input = if join.is_none() { scope.human_input.clone() } else { None };
+
+            loop {
+                // Branches hand their output back to the fan-out at the join node
+                if join == Some(current_id) {
+                    break;
+                }
+
+                // Stop cancelled and overdue runs before starting another node
+                if let Some(error) = scope.stop_reason() {
+                    chain.error = Some(error);
+                    break;
+                }
+
+                // First, check if the current node exists
+                if !self.nodes.contains_key(&current_id) {
+                    chain.error = Some(NodeError::NodeNotFound(current_id).into());
+                    break;
+                }
+
+                // Pause before interrupt points, unless resuming with human input
+                let resumed_with = human_input.take();
+                if let Some(prompt) = self.interrupts.get(&current_id) {
+                    if join.is_some() {
+                        chain.error = Some(branch_interrupt_error(current_id));
+                        break;
+                    }
+                    if resumed_with.is_none() {
+                        let saved = self
+                            .save_checkpoint(scope, &chain, Some(current_id), &current_input, Some(prompt))
+                            .await;
+                        match saved {
+                            Ok(()) => chain.paused = Some((current_id, prompt.clone())),
+                            Err(e) => chain.error = Some(e),
+                        }
+                        break;
+                    }
+                }
+
+                // Enforce the step budget and the per-node visit limit
+                if let Some(max_steps) = self.max_steps {
+                    if scope.steps_taken.fetch_add(1, Ordering::SeqCst) >= max_steps {
+                        chain.error = Some(AgentError::MaxIterationsExceeded.into());
+                        break;
+                    }
+                }
+                let visit_count = visits.entry(current_id).or_insert(0);
+                if let Some(max_visits) = self.max_visits_per_node {
+                    if *visit_count >= max_visits {
+                        let path = cycle_path(&chain.steps, current_id);
+                        chain.error = Some(GraphError::CycleDetected(path).into());
+                        break;
+                    }
+                }
+                *visit_count += 1;
+
+                let started_at = SystemTime::now();
+                let node = &self.nodes[&current_id];
+                scope.ctx.emit(GraphEvent::NodeStarted {
+                    node_id: current_id,
+                    agent_name: node.name.clone(),
+                    input: current_input.clone(),
+                });
+
+                if let Some(fan_out) = self.fan_outs.get(&current_id) {
+                    let merged = self
+                        .run_branches(&fan_out.branches, &current_input, fan_out.join, &fan_out.strategy, scope, &mut chain)
+                        .await;
+
+                    chain.steps.push(Step {
+                        node_id: current_id,
+                        agent_name: node.name.clone(),
+                        input: current_input,
+                        output: merged.clone(),
+                        next_id: Some(fan_out.join),
+                        started_at,
+                        finished_at: SystemTime::now(),
+                        error: chain.error.as_ref().map(|e| e.to_string()),
+                        substeps: Vec::new(),
+                        metadata: Default::default(),
+                    });
+                    scope.ctx.emit(GraphEvent::NodeFinished(chain.steps[chain.steps.len() - 1].clone()));
+                    chain.output = merged.clone();
+
+                    if chain.error.is_some() {
+                        break;
+                    }
+                    if join.is_none() {
+                        let saved = self
+                            .save_checkpoint(scope, &chain, Some(fan_out.join), &merged, None)
+                            .await;
+                        if let Err(e) = saved {
+                            chain.error = Some(e);
+                            break;
+                        }
+                    }
+                    current_id = fan_out.join;
+                    current_input = merged;
+                    continue;
+                }
+
+                // The node's own tools take precedence over the shared ones
                 let combined_registry = CombinedToolRegistry::new(
-                    &*node_tool_registry_ptr as &(dyn ToolRegistryTrait + Send + Sync),
-                    &*tool_registry_ptr as &(dyn ToolRegistryTrait + Send + Sync),
+                    node.tool_registry.as_ref(),
+                    self.tool_registry.as_ref(),
                 );
-                
-                // Now get mutable access to the node and run the agent
-                let nodes_mut = &mut *nodes_ptr;
-                let node = nodes_mut.get_mut(&current_id).unwrap();
-                node.agent.run(&current_input, &combined_registry).await
-            };
+                let mut agent = node.agent.lock().await;
+                let agent_name = agent.get_name().to_string();
+                let node_ctx = scope.ctx.for_node(current_id).with_human_input(resumed_with);
+
+                // In a subgraph, the outer graph's tools come last; calls are
+                // reported once, by the node that made them
+                let layered;
+                let tools: &dyn ToolRegistryTrait = match scope.outer_tools {
+                    Some(outer) => {
+                        layered = CombinedToolRegistry::new(&combined_registry, outer);
+                        &layered
+                    }
+                    None => &combined_registry,
+                };
+                let registry = EventToolRegistry::new(tools, &node_ctx);
+                // Dropping the agent's future on cancellation or timeout aborts
+                // whatever it is waiting for, e.g. an HTTP request
+                let mut agent_input = current_input.clone();
+                let node_deadline = self
+                    .node_timeouts
+                    .get(&current_id)
+                    .map(|timeout| (Instant::now() + *timeout, *timeout));
+                let outcome: LLMGraphResult<AgentResponse> = tokio::select! {
+                    biased;
+                    _ = wait_for_cancellation(scope.ctx.cancellation()) => Err(GraphError::Cancelled.into()),
+                    _ = sleep_until_deadline(scope.deadline) => Err(GraphError::DeadlineExceeded.into()),
+                    _ = sleep_until_deadline(node_deadline.map(|(deadline, _)| deadline)) => {
+                        let timeout = node_deadline.map(|(_, timeout)| timeout).unwrap_or_default();
+                        Err(NodeError::Timeout { node_id: current_id, timeout }.into())
+                    }
+                    result = self.invoke_agent(current_id, &mut **agent, &mut agent_input, &registry, &node_ctx) => result,
+                };
+                let response = match outcome {
+                    Ok(response) => response,
+                    Err(error) => {
+                        drop(agent);
+                        let frame = ErrorFrame::node(current_id, &agent_name);
+                        chain.steps.push(Step {
+                            node_id: current_id,
+                            agent_name,
+                            input: agent_input,
+                            output: String::new(),
+                            next_id: None,
+                            started_at,
+                            finished_at: SystemTime::now(),
+                            error: Some(error.to_string()),
+                            substeps: node_ctx.take_substeps(),
+                            metadata: Default::default(),
+                        });
+                        scope.ctx.emit(GraphEvent::NodeFinished(chain.steps[chain.steps.len() - 1].clone()));
+                        chain.error = Some(error.within(frame));
+                        break;
+                    }
+                };
+                if self.checkpoint_store.is_some() {
+                    if let Some(snapshot) = agent.snapshot() {
+                        chain.agent_states.insert(current_id, snapshot);
+                    }
+                }
+                drop(agent);
+
+                let AgentResponse { output, action, metadata, .. } = response;
+                let mut interrupt = node_ctx.take_interrupt();
+                let mut fork = None;
+                let (next_id, error) = match action {
+                    AgentAction::Next(next) => (Some(next), None),
+                    AgentAction::End => (None, None),
+                    AgentAction::Fail(error) => (None, Some(error)),
+                    AgentAction::Interrupt(prompt) => {
+                        interrupt = Some(prompt);
+                        (None, None)
+                    }
+                    AgentAction::Fork { branches, join: fork_join } => {
+                        let error = self.check_fork(current_id, &branches, fork_join).err();
+                        fork = Some((branches, fork_join));
+                        (Some(fork_join), error)
+                    }
+                };
+                let error = error.or_else(|| match next_id {
+                    _ if interrupt.is_some() && join.is_some() => Some(branch_interrupt_error(current_id)),
+                    _ if fork.is_some() => None,
+                    Some(next) if !self.nodes.contains_key(&next) => {
+                        Some(NodeError::NodeNotFound(next).into())
+                    }
+                    Some(next) if self.strict_edges && !self.has_edge(current_id, next) => {
+                        Some(GraphError::InvalidEdge { from: current_id, to: next }.into())
+                    }
+                    _ => None,
+                });
+
+                let frame = ErrorFrame::node(current_id, &agent_name);
+                chain.steps.push(Step {
+                    node_id: current_id,
+                    agent_name,
+                    input: agent_input,
+                    output: output.clone(),
+                    next_id,
+                    started_at,
+                    finished_at: SystemTime::now(),
+                    error: error.as_ref().map(|e| e.to_string()),
+                    substeps: node_ctx.take_substeps(),
+                    metadata,
+                });
+                scope.ctx.emit(GraphEvent::NodeFinished(chain.steps[chain.steps.len() - 1].clone()));
+                chain.output = output.clone();
+
+                if let Some(error) = error {
+                    chain.error = Some(error.within(frame));
+                    break;
+                }
+
+                // An agent asked to pause: run this node again when resumed
+                if let Some(prompt) = interrupt {
+                    let saved = self
+                        .save_checkpoint(scope, &chain, Some(current_id), &current_input, Some(&prompt))
+                        .await;
+                    match saved {
+                        Ok(()) => chain.paused = Some((current_id, prompt)),
+                        Err(e) => chain.error = Some(e),
+                    }
+                    break;
+                }
+
+                // An agent forked: run the branches with its output, then join
+                let (next_id, output) = match fork {
+                    Some((branches, fork_join)) => {
+                        let merged = self
+                            .run_branches(&branches, &output, fork_join, &CombineStrategy::Concatenate, scope, &mut chain)
+                            .await;
+                        chain.output = merged.clone();
+                        if chain.error.is_some() {
+                            break;
+                        }
+                        (Some(fork_join), merged)
+                    }
+                    None => (next_id, output),
+                };
 
-            result.push_str(&output);
-            result.push('\n');
+                if join.is_none() {
+                    if let Err(e) = self.save_checkpoint(scope, &chain, next_id, &output, None).await {
+                        chain.error = Some(e);
+                        break;
+                    }
+                }
 
-            match next_id {
-                Some(next) => {
-                    if self.nodes.contains_key(&next) {
+                match next_id {
+                    Some(next) => {
                         current_id = next;
                         current_input = output;
-                    } else {
-                        result.push_str(&format!("Error: Node {} does not exist\n", next));
-                        break;
                     }
+                    None => break,
                 }
-                None => break,
             }
+
+            chain
         }
+        .boxed()
+    }
 
-        result
+    /// Run `branches` concurrently with `input` until they reach `join`, add
+    /// their steps to `chain` and merge their outputs with `strategy`.
+    async fn run_branches(
+        &self,
+        branches: &[i32],
+        input: &str,
+        join: i32,
+        strategy: &CombineStrategy,
+        scope: &RunScope<'_>,
+        chain: &mut Chain,
+    ) -> String {
+        let runs = branches
+            .iter()
+            .map(|&branch| self.run_chain(branch, input.to_string(), Some(join), scope, Chain::new()));
+        let mut outputs = Vec::new();
+        for branch in join_all(runs).await {
+            chain.steps.extend(branch.steps);
+            chain.agent_states.extend(branch.agent_states);
+            if chain.error.is_none() {
+                chain.error = branch.error;
+            }
+            outputs.push(branch.output);
+        }
+        strategy.combine(outputs)
+    }
+
+    /// Check a fork requested by the agent of `from`.
+    fn check_fork(&self, from: i32, branches: &[i32], join: i32) -> LLMGraphResult<()> {
+        if branches.is_empty() {
+            return Err(AgentError::InvalidOutput(format!("Node {} forked without branches", from)).into());
+        }
+        if let Some(&missing) = branches.iter().chain([&join]).find(|n| !self.nodes.contains_key(n)) {
+            return Err(NodeError::NodeNotFound(missing).into());
+        }
+        match branches.iter().find(|&&branch| self.strict_edges && !self.has_edge(from, branch)) {
+            Some(&branch) => Err(GraphError::InvalidEdge { from, to: branch }.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl RunScope<'_> {
+    /// The error to stop the run with, if it was cancelled or is overdue
+    fn stop_reason(&self) -> Option<LLMGraphError> {
+        if self.ctx.is_cancelled() {
+            return Some(GraphError::Cancelled.into());
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Some(GraphError::DeadlineExceeded.into()),
+            _ => None,
+        }
+    }
+}
+
+/// Stream the events of the run `run` starts with an event sender, ending
+/// with `RunFinished` or `RunFailed`. The run only progresses while the
+/// stream is polled.
+fn event_stream<'a, R, F>(run: R) -> impl Stream<Item = GraphEvent> + Send + 'a
+where
+    R: FnOnce(UnboundedSender<GraphEvent>) -> F,
+    F: std::future::Future<Output = RunResult> + Send + 'a,
+{
+    let (sender, receiver) = mpsc::unbounded();
+    let result = run(sender.clone());
+    let run = async move {
+        let result = result.await;
+        let event = if result.error.is_some() {
+            GraphEvent::RunFailed(result)
+        } else {
+            GraphEvent::RunFinished(result)
+        };
+        let _ = sender.unbounded_send(event);
+    };
+
+    // Drive the run alongside the receiver; the receiver ends once the
+    // run has finished and dropped every sender
+    let driver = run.into_stream().filter_map(|_| futures::future::ready(None));
+    stream::select(driver, receiver)
+}
+
+/// Wait until `token` is cancelled; forever without a token.
+async fn wait_for_cancellation(token: Option<&CancellationToken>) {
+    match token {
+        Some(token) => token.cancelled().await,
+        None => futures::future::pending().await,
+    }
+}
+
+/// Wait until `deadline`; forever without a deadline.
+async fn sleep_until_deadline(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => futures::future::pending().await,
     }
-}
\ No newline at end of file
+}
+
+/// Visit counts, latencies and hop counts of a run, for rendering.
+#[derive(Default)]
+struct TraceOverlay {
+    visits: HashMap<i32, (usize, Duration)>,
+    hops: HashMap<(i32, i32), usize>,
+}
+
+impl TraceOverlay {
+    fn new(steps: &[Step]) -> Self {
+        let mut trace = Self::default();
+        for step in steps {
+            let (visits, latency) = trace.visits.entry(step.node_id).or_default();
+            *visits += 1;
+            *latency += step.duration();
+            if let Some(next) = step.next_id {
+                *trace.hops.entry((step.node_id, next)).or_default() += 1;
+            }
+        }
+        trace
+    }
+
+    fn hop(&self, from: i32, to: i32) -> usize {
+        self.hops.get(&(from, to)).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum EdgeKind {
+    Declared,   // Added with add_edge, add_directed_edge or add_fan_out
+    Join,       // From a fan-out to its join node
+    Undeclared, // Taken in a trace without an edge
+}
+
+struct RenderedEdge {
+    from: i32,
+    to: i32,
+    both_ways: bool,
+    kind: EdgeKind,
+    taken: usize,
+}
+
+impl RenderedEdge {
+    /// "join" for join edges, plus how often a traced run took the edge
+    fn label(&self) -> Option<String> {
+        match (self.kind, self.taken) {
+            (EdgeKind::Join, 0) => Some("join".to_string()),
+            (EdgeKind::Join, taken) => Some(format!("join {}x", taken)),
+            (_, 0) => None,
+            (_, taken) => Some(format!("{}x", taken)),
+        }
+    }
+}
+
+/// Build the result of a run from its main chain.
+fn finish_run(scope: &RunScope<'_>, chain: Chain) -> RunResult {
+    let run_id = scope.ctx.run_id().to_string();
+    let status = match (&chain.error, chain.paused) {
+        (Some(_), _) => RunStatus::Failed,
+        (None, Some((node, prompt))) => RunStatus::Paused {
+            run_id: run_id.clone(),
+            node,
+            prompt,
+        },
+        (None, None) => RunStatus::Completed,
+    };
+
+    let (error, error_context) = match chain.error {
+        Some(error) => {
+            let (mut context, error) = error.into_parts();
+            context.run_id = Some(run_id.clone());
+            (Some(*error), Some(context))
+        }
+        None => (None, None),
+    };
+    RunResult {
+        error,
+        error_context,
+        run_id,
+        output: chain.output,
+        steps: chain.steps,
+        state: scope.ctx.state(),
+        status,
+    }
+}
+
+/// The error for an interrupt inside a fan-out branch, which can't be paused.
+fn branch_interrupt_error(node_id: i32) -> LLMGraphError {
+    AgentError::InvalidOutput(format!(
+        "Node {} requested an interrupt inside a fan-out branch",
+        node_id
+    ))
+    .into()
+}
+
+/// The node path from the most recent visit of `node_id` back to it.
+fn cycle_path(steps: &[Step], node_id: i32) -> Vec<i32> {
+    let start = steps
+        .iter()
+        .rposition(|step| step.node_id == node_id)
+        .unwrap_or(steps.len());
+    let mut path: Vec<i32> = steps[start..].iter().map(|step| step.node_id).collect();
+    path.push(node_id);
+    path
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,65 @@
+//! Middleware around agent invocations.
+//!
+//! Middleware registered with [`Graph::add_middleware`](crate::Graph::add_middleware)
+//! or [`Graph::add_node_middleware`](crate::Graph::add_node_middleware) sees
+//! every agent invocation, for cross-cutting behavior such as logging,
+//! redaction, metrics, input rewriting and output guards, without wrapping
+//! each agent by hand.
+
+use async_trait::async_trait;
+use crate::errors::LLMGraphResult;
+use crate::models::run::RunContext;
+
+/// Hooks called around each agent invocation.
+///
+/// `before` hooks run in registration order, global middleware first, and
+/// `after` hooks run in reverse order, so the first middleware registered is
+/// the outermost. Both hooks default to doing nothing.
+///
+/// A hook can rewrite the input or output in place, and `after` can override
+/// the route by changing `next`, which holds the node of an
+/// [`AgentAction::Next`](crate::AgentAction::Next) and `None` otherwise.
+/// Changing it replaces the agent's action with `Next`, or with `End` for
+/// `None`. Returning an error stops the run with that error, e.g.
+/// [`GraphError::Stopped`](crate::errors::GraphError::Stopped).
+///
+/// # Example
+/// ```rust
+/// use async_trait::async_trait;
+/// use llmgraph::errors::LLMGraphResult;
+/// use llmgraph::models::middleware::Middleware;
+/// use llmgraph::{Graph, RunContext};
+/// use std::sync::Arc;
+///
+/// struct Redact;
+///
+/// #[async_trait]
+/// impl Middleware for Redact {
+///     async fn before(&self, _node: i32, input: &mut String, _ctx: &RunContext) -> LLMGraphResult<()> {
+///         *input = input.replace("password", "********");
+///         Ok(())
+///     }
+/// }
+///
+/// let mut graph = Graph::new();
+/// graph.add_middleware(Arc::new(Redact));
+/// ```
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Called before the agent of `node` runs with `input`.
+    async fn before(&self, _node: i32, _input: &mut String, _ctx: &RunContext) -> LLMGraphResult<()> {
+        Ok(())
+    }
+
+    /// Called after the agent of `node` returned `output` and chose `next`.
+    /// A changed `next` only reroutes agents that continued or ended.
+    async fn after(
+        &self,
+        _node: i32,
+        _output: &mut String,
+        _next: &mut Option<i32>,
+        _ctx: &RunContext,
+    ) -> LLMGraphResult<()> {
+        Ok(())
+    }
+}
@@ -1,6 +1,12 @@
 pub mod message;
 pub mod graph;
 pub mod tools;
+pub mod run;
+pub mod checkpoint;
+pub mod events;
+pub mod spec;
+pub mod middleware;
+pub mod response;
 
 // Re-export the Message struct from tools module for convenience
 pub use tools::Message;
\ No newline at end of file
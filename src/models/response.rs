@@ -0,0 +1,137 @@
+//! Typed agent results.
+//!
+//! [`Agent::execute`](crate::Agent::execute) returns an [`AgentResponse`]:
+//! the agent's output, an [`AgentAction`] telling the graph what to do next,
+//! and free-form metadata recorded in the trace. Agents implementing only
+//! [`Agent::run`](crate::Agent::run) are adapted from their
+//! `(String, Option<i32>)` result.
+
+use serde_json::{Map, Value};
+use crate::errors::LLMGraphError;
+
+/// What the graph should do after an agent ran.
+#[derive(Debug)]
+pub enum AgentAction {
+    /// Continue with the given node
+    Next(i32),
+    /// End the chain
+    End,
+    /// Stop the run with an error
+    Fail(LLMGraphError),
+    /// Run the branches concurrently with the agent's output, then continue
+    /// at `join` with their outputs joined by newlines (like
+    /// [`Graph::add_fan_out`](crate::Graph::add_fan_out), decided at run time)
+    Fork { branches: Vec<i32>, join: i32 },
+    /// Pause the run and ask a human (like [`RunContext::interrupt`](crate::RunContext::interrupt))
+    Interrupt(String),
+}
+
+/// The result of an agent invocation.
+///
+/// # Example
+/// ```rust
+/// use llmgraph::errors::AgentError;
+/// use llmgraph::models::response::{AgentAction, AgentResponse};
+/// use serde_json::json;
+///
+/// let response = AgentResponse::next("Routing to billing", 2).with_metadata("confidence", json!(0.9));
+/// assert!(matches!(response.action, AgentAction::Next(2)));
+///
+/// let failed = AgentResponse::fail(AgentError::InvalidInput("empty ticket".to_string()));
+/// assert!(failed.is_failure());
+/// ```
+#[derive(Debug)]
+pub struct AgentResponse {
+    /// The agent's output
+    pub output: String,
+    /// What the graph should do next
+    pub action: AgentAction,
+    /// Free-form metadata, recorded in [`Step::metadata`](crate::Step::metadata)
+    pub metadata: Map<String, Value>,
+    /// Whether this was adapted from an [`Agent::run`](crate::Agent::run)
+    /// result, whose failures are only reported in the output text
+    pub(crate) from_run: bool,
+}
+
+impl AgentResponse {
+    /// Create a response with the given action
+    pub fn new(output: impl Into<String>, action: AgentAction) -> Self {
+        Self {
+            output: output.into(),
+            action,
+            metadata: Map::new(),
+            from_run: false,
+        }
+    }
+
+    /// Continue with `node_id`
+    pub fn next(output: impl Into<String>, node_id: i32) -> Self {
+        Self::new(output, AgentAction::Next(node_id))
+    }
+
+    /// End the chain
+    pub fn end(output: impl Into<String>) -> Self {
+        Self::new(output, AgentAction::End)
+    }
+
+    /// Stop the run with `error`
+    pub fn fail(error: impl Into<LLMGraphError>) -> Self {
+        Self::new(String::new(), AgentAction::Fail(error.into()))
+    }
+
+    /// Run `branches` concurrently, then continue at `join`
+    pub fn fork(output: impl Into<String>, branches: Vec<i32>, join: i32) -> Self {
+        Self::new(output, AgentAction::Fork { branches, join })
+    }
+
+    /// Pause the run with `prompt` for a human
+    pub fn interrupt(output: impl Into<String>, prompt: impl Into<String>) -> Self {
+        Self::new(output, AgentAction::Interrupt(prompt.into()))
+    }
+
+    /// Add a metadata entry
+    pub fn with_metadata(mut self, key: impl Into<String>, value: Value) -> Self {
+        self.metadata.insert(key.into(), value);
+        self
+    }
+
+    /// Whether the agent failed
+    pub fn is_failure(&self) -> bool {
+        matches!(self.action, AgentAction::Fail(_))
+    }
+
+    /// The node the chain continues with, for `Next`
+    pub fn next_id(&self) -> Option<i32> {
+        match self.action {
+            AgentAction::Next(node_id) => Some(node_id),
+            _ => None,
+        }
+    }
+}
+
+/// Adapt the result of [`Agent::run`](crate::Agent::run): `Some(id)` becomes
+/// `Next(id)` and `None` becomes `End`.
+impl From<(String, Option<i32>)> for AgentResponse {
+    fn from((output, next_id): (String, Option<i32>)) -> Self {
+        let mut response = match next_id {
+            Some(node_id) => Self::next(output, node_id),
+            None => Self::end(output),
+        };
+        response.from_run = true;
+        response
+    }
+}
+
+/// Convert back to the `(output, next)` shape of [`Agent::run`](crate::Agent::run).
+///
+/// Only `Next` keeps a route. A failure without output is reported as
+/// `Error: ...` in the output.
+impl From<AgentResponse> for (String, Option<i32>) {
+    fn from(response: AgentResponse) -> Self {
+        match response.action {
+            AgentAction::Next(node_id) => (response.output, Some(node_id)),
+            AgentAction::Fail(error) if response.output.is_empty() => (format!("Error: {}", error), None),
+            _ => (response.output, None),
+        }
+    }
+}
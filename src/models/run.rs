@@ -0,0 +1,442 @@
+//! Run options, run-scoped state and execution traces for graph runs.
+//!
+//! This module provides the structured output of [`Graph::run_traced`](crate::Graph::run_traced):
+//! the final output, the error that stopped the run (if any), the final run
+//! state and one [`Step`] per agent invocation. It also provides
+//! [`RunContext`], the handle agents use to read and write the run's state.
+
+use futures::channel::mpsc::UnboundedSender;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
+use crate::errors::{ErrorContext, LLMGraphError, LLMGraphResult};
+use crate::models::events::GraphEvent;
+
+/// Options for a single graph run.
+///
+/// # Example
+/// ```rust
+/// use llmgraph::models::run::RunOptions;
+/// use serde_json::{json, Map};
+///
+/// let mut state = Map::new();
+/// state.insert("customer".to_string(), json!("ACME"));
+/// let options = RunOptions::new().with_state(state);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Initial run state, visible to every agent
+    pub state: Map<String, Value>,
+    /// The run ID; generated when not set
+    pub run_id: Option<String>,
+    /// Token to cancel the run from outside
+    pub cancellation: Option<CancellationToken>,
+    /// When the run must have finished
+    pub deadline: Option<Instant>,
+}
+
+impl RunOptions {
+    /// Create default run options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the initial run state
+    pub fn with_state(mut self, state: Map<String, Value>) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Set the run ID, e.g. to resume the run later under a known name
+    pub fn with_run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Stop the run when `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Stop the run if it hasn't finished by `deadline`
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stop the run if it hasn't finished within `timeout` from now
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+}
+
+/// A token to cancel graph runs from outside.
+///
+/// Clones share the same state, so one clone can be handed to the run and
+/// another kept to cancel it. Cancelling drops the agent that is currently
+/// running, which also aborts its in-flight HTTP requests such as
+/// [`generate_full_response`](crate::generate::generate::generate_full_response).
+///
+/// # Example
+/// ```rust
+/// use llmgraph::models::run::{CancellationToken, RunOptions};
+///
+/// let token = CancellationToken::new();
+/// let options = RunOptions::new().with_cancellation(token.clone());
+/// // e.g. from a "stop" button handler:
+/// token.cancel();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancellationState>,
+}
+
+#[derive(Debug, Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel every run using this token
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Whether the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the token is cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            // Register before checking, so a concurrent cancel can't be missed
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Generate a run ID that is unique within this process.
+pub(crate) fn new_run_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!("run-{}-{}", millis, COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Run-scoped context handed to every agent invocation.
+///
+/// The state is a JSON object shared by all nodes of a run, including
+/// concurrent fan-out branches, and is returned in [`RunResult::state`].
+///
+/// # Example
+/// ```rust
+/// use llmgraph::models::run::RunContext;
+/// use serde_json::json;
+///
+/// let ctx = RunContext::new(0, Default::default());
+/// ctx.set("ticket_id", json!(4711));
+/// assert_eq!(ctx.get("ticket_id"), Some(json!(4711)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RunContext {
+    run_id: String,
+    node_id: i32,
+    state: Arc<Mutex<Map<String, Value>>>,
+    human_input: Option<String>,
+    interrupt: Arc<Mutex<Option<String>>>,
+    events: Option<UnboundedSender<GraphEvent>>,
+    substeps: Arc<Mutex<Vec<Step>>>,
+    cancellation: Option<CancellationToken>,
+    in_node: bool, // Whether this is the context of a graph node
+}
+
+impl RunContext {
+    /// Create a context for `node_id` with the given initial state
+    pub fn new(node_id: i32, state: Map<String, Value>) -> Self {
+        Self {
+            run_id: String::new(),
+            node_id,
+            state: Arc::new(Mutex::new(state)),
+            human_input: None,
+            interrupt: Arc::new(Mutex::new(None)),
+            events: None,
+            substeps: Arc::new(Mutex::new(Vec::new())),
+            cancellation: None,
+            in_node: false,
+        }
+    }
+
+    /// Stop the run when `token` is cancelled
+    pub(crate) fn with_cancellation(mut self, token: Option<CancellationToken>) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Send events of this run to `events`
+    pub(crate) fn with_events(mut self, events: Option<UnboundedSender<GraphEvent>>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Set the ID of the run this context belongs to
+    pub fn with_run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = run_id.into();
+        self
+    }
+
+    /// A context for another node of the same run, sharing its state
+    pub(crate) fn for_node(&self, node_id: i32) -> Self {
+        Self {
+            run_id: self.run_id.clone(),
+            node_id,
+            state: self.state.clone(),
+            human_input: None,
+            interrupt: Arc::new(Mutex::new(None)),
+            events: self.events.clone(),
+            substeps: Arc::new(Mutex::new(Vec::new())),
+            cancellation: self.cancellation.clone(),
+            in_node: true,
+        }
+    }
+
+    /// Attach the human input a paused run was resumed with
+    pub(crate) fn with_human_input(mut self, human_input: Option<String>) -> Self {
+        self.human_input = human_input;
+        self
+    }
+
+    /// The ID of the run
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// The ID of the node being executed
+    pub fn node_id(&self) -> i32 {
+        self.node_id
+    }
+
+    /// Whether the agent runs as a node of a graph, rather than on its own
+    pub(crate) fn in_node(&self) -> bool {
+        self.in_node
+    }
+
+    /// Get a value from the run state
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.state.lock().unwrap().get(key).cloned()
+    }
+
+    /// Store a value in the run state, returning the previous value
+    pub fn set(&self, key: impl Into<String>, value: Value) -> Option<Value> {
+        self.state.lock().unwrap().insert(key.into(), value)
+    }
+
+    /// Remove a value from the run state
+    pub fn remove(&self, key: &str) -> Option<Value> {
+        self.state.lock().unwrap().remove(key)
+    }
+
+    /// Modify the run state in place, e.g. to update several keys atomically
+    pub fn update<R>(&self, f: impl FnOnce(&mut Map<String, Value>) -> R) -> R {
+        f(&mut self.state.lock().unwrap())
+    }
+
+    /// A copy of the current run state
+    pub fn state(&self) -> Map<String, Value> {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// The human input passed to [`Graph::resume_with`](crate::Graph::resume_with),
+    /// when this node is the one the run was paused at
+    pub fn human_input(&self) -> Option<&str> {
+        self.human_input.as_deref()
+    }
+
+    /// Ask the graph to pause the run after this agent returns.
+    ///
+    /// The run ends with [`RunStatus::Paused`] and `prompt`. A later
+    /// [`Graph::resume_with`](crate::Graph::resume_with) runs this node again
+    /// with the same input, and [`RunContext::human_input`] set. Without a
+    /// checkpoint store the run fails instead, as it could not be resumed.
+    pub fn interrupt(&self, prompt: impl Into<String>) {
+        *self.interrupt.lock().unwrap() = Some(prompt.into());
+    }
+
+    /// Whether the run has been cancelled, for agents that loop on their own
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.as_ref().is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// The run's cancellation token, if it has one
+    pub(crate) fn cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// Report part of this agent's output as a [`GraphEvent::TokenDelta`].
+    ///
+    /// Does nothing unless the run was started with
+    /// [`Graph::run_stream`](crate::Graph::run_stream).
+    pub fn emit_token(&self, delta: impl Into<String>) {
+        self.emit(GraphEvent::TokenDelta {
+            node_id: self.node_id,
+            delta: delta.into(),
+        });
+    }
+
+    /// Send an event to the run's event stream, if there is one
+    pub(crate) fn emit(&self, event: GraphEvent) {
+        if let Some(events) = &self.events {
+            // The receiver may have been dropped; the run continues regardless
+            let _ = events.unbounded_send(event);
+        }
+    }
+
+    /// Take the prompt of a pending interrupt request
+    pub(crate) fn take_interrupt(&self) -> Option<String> {
+        self.interrupt.lock().unwrap().take()
+    }
+
+    /// Record the steps of a nested run, to be attached to this node's step
+    pub(crate) fn record_substeps(&self, steps: Vec<Step>) {
+        self.substeps.lock().unwrap().extend(steps);
+    }
+
+    /// Take the steps recorded with `record_substeps`
+    pub(crate) fn take_substeps(&self) -> Vec<Step> {
+        std::mem::take(&mut *self.substeps.lock().unwrap())
+    }
+}
+
+/// How a graph run ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunStatus {
+    /// The last agent ended the chain
+    Completed,
+    /// The run stopped with an error (see [`RunResult::error`])
+    Failed,
+    /// The run is waiting for human input (see [`Graph::resume_with`](crate::Graph::resume_with))
+    Paused {
+        /// The ID of the paused run
+        run_id: String,
+        /// The node that runs when the run is resumed
+        node: i32,
+        /// What the human is asked for
+        prompt: String,
+    },
+}
+
+/// A single agent invocation recorded during a graph run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    /// The ID of the node that ran
+    pub node_id: i32,
+    /// The agent's name as reported by `get_name()`
+    pub agent_name: String,
+    /// The input passed to the agent
+    pub input: String,
+    /// The output returned by the agent
+    pub output: String,
+    /// The next node chosen by the agent (None ends the chain)
+    pub next_id: Option<i32>,
+    /// When the agent was invoked
+    pub started_at: SystemTime,
+    /// When the agent returned
+    pub finished_at: SystemTime,
+    /// The error raised by this step, if any
+    pub error: Option<String>,
+    /// The steps of a subgraph run by this step's agent (see
+    /// [`SubgraphAgent`](crate::agents::SubgraphAgent))
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub substeps: Vec<Step>,
+    /// Metadata the agent attached to its [`AgentResponse`](crate::AgentResponse)
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub metadata: Map<String, Value>,
+}
+
+impl Step {
+    /// Wall-clock time spent in this step.
+    pub fn duration(&self) -> Duration {
+        self.finished_at
+            .duration_since(self.started_at)
+            .unwrap_or_default()
+    }
+}
+
+/// The structured result of a graph run.
+///
+/// # Example
+/// ```rust,ignore
+/// # use llmgraph::Graph;
+/// # let graph = Graph::new();
+/// let result = graph.run_traced(0, "Process this task").await;
+/// for step in &result.steps {
+///     println!("{} ({}) -> {:?}", step.node_id, step.agent_name, step.next_id);
+/// }
+/// println!("Final output: {}", result.output);
+/// ```
+#[derive(Debug)]
+pub struct RunResult {
+    /// The ID of the run, used to resume it from a checkpoint
+    pub run_id: String,
+    /// The output of the last agent that ran
+    pub output: String,
+    /// Every agent invocation, in execution order
+    pub steps: Vec<Step>,
+    /// The error that stopped the run, if any
+    pub error: Option<LLMGraphError>,
+    /// Where the error happened: the run ID and the path of nodes and
+    /// agents through subgraphs, retries and parallel agents
+    pub error_context: Option<ErrorContext>,
+    /// The run state after the last step
+    pub state: Map<String, Value>,
+    /// How the run ended
+    pub status: RunStatus,
+}
+
+impl RunResult {
+    /// Whether the run finished without an error.
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Whether the run is waiting for human input.
+    pub fn is_paused(&self) -> bool {
+        matches!(self.status, RunStatus::Paused { .. })
+    }
+
+    /// All step outputs joined by newlines, as returned by [`Graph::run`](crate::Graph::run).
+    pub fn transcript(&self) -> String {
+        let mut transcript = String::new();
+        for step in &self.steps {
+            transcript.push_str(&step.output);
+            transcript.push('\n');
+        }
+        transcript
+    }
+
+    /// Convert into the final output, or the error that stopped the run.
+    pub fn into_result(self) -> LLMGraphResult<String> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.output),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Declarative graph specs.
+//!
+//! A [`GraphSpec`] describes a graph in JSON or YAML: its nodes by agent type
+//! and config, its edges, the start node and the tools of each node. Agents
+//! are built with an [`AgentFactory`], and tools are taken by name from a
+//! [`ToolRegistry`] of available tools, so routing can change without a
+//! recompile. YAML support requires the `yaml` feature.
+//!
+//! ```yaml
+//! start: 0
+//! max_steps: 20
+//! nodes:
+//!   - id: 0
+//!     type: router
+//!     config:
+//!       routes:
+//!         - { pattern: "refund|invoice", target_node: 1 }
+//!       default: 2
+//!   - id: 1
+//!     type: summarizer
+//!     config: { style: bullets, max_length: 200 }
+//!     tools: [lookup_order]
+//!   - id: 2
+//!     type: summarizer
+//! edges:
+//!   - { from: 0, to: 1, directed: true }
+//!   - { from: 0, to: 2, directed: true }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use crate::agents::factory::{AgentFactory, AgentSpec};
+use crate::errors::{LLMGraphError, LLMGraphResult, NodeError};
+use crate::models::graph::Graph;
+use crate::models::tools::ToolRegistry;
+
+/// A graph described declaratively.
+///
+/// # Example
+/// ```rust
+/// use llmgraph::agents::AgentFactory;
+/// use llmgraph::models::spec::GraphSpec;
+/// use llmgraph::ToolRegistry;
+///
+/// let spec = GraphSpec::from_json(r#"{
+///     "start": 0,
+///     "nodes": [
+///         {"id": 0, "type": "router", "config": {"routes": [{"pattern": "bug", "target_node": 1}]}},
+///         {"id": 1, "type": "summarizer"}
+///     ],
+///     "edges": [{"from": 0, "to": 1}]
+/// }"#).unwrap();
+/// let graph = spec.build(&AgentFactory::new(), &ToolRegistry::new()).unwrap();
+/// assert!(graph.validate(spec.start).is_ok());
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphSpec {
+    /// The node runs start at
+    pub start: i32,
+    /// The nodes of the graph
+    pub nodes: Vec<NodeSpec>,
+    /// The edges of the graph
+    #[serde(default)]
+    pub edges: Vec<EdgeSpec>,
+    /// See [`Graph::set_strict_edges`]
+    #[serde(default)]
+    pub strict_edges: bool,
+    /// See [`Graph::set_max_steps`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_steps: Option<usize>,
+    /// See [`Graph::set_max_visits_per_node`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_visits_per_node: Option<usize>,
+}
+
+/// A node of a [`GraphSpec`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeSpec {
+    /// The node ID
+    pub id: i32,
+    /// The agent type and its config
+    #[serde(flatten)]
+    pub agent: AgentSpec,
+    /// Names of tools registered for this node only
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+}
+
+/// An edge of a [`GraphSpec`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeSpec {
+    /// The source node
+    pub from: i32,
+    /// The target node
+    pub to: i32,
+    /// Only allow hops from `from` to `to` (see [`Graph::add_directed_edge`])
+    #[serde(default)]
+    pub directed: bool,
+}
+
+impl GraphSpec {
+    /// Parse a spec from JSON
+    pub fn from_json(json: &str) -> LLMGraphResult<Self> {
+        serde_json::from_str(json).map_err(|e| LLMGraphError::SpecError(e.to_string()))
+    }
+
+    /// Parse a spec from YAML. Requires the `yaml` feature.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(yaml: &str) -> LLMGraphResult<Self> {
+        serde_yaml::from_str(yaml).map_err(|e| LLMGraphError::SpecError(e.to_string()))
+    }
+
+    /// Build the graph, creating agents with `factory` and taking each node's
+    /// tools from `tools`.
+    pub fn build(&self, factory: &AgentFactory, tools: &ToolRegistry) -> LLMGraphResult<Graph> {
+        let mut graph = Graph::new();
+        graph.set_strict_edges(self.strict_edges);
+        graph.set_max_steps(self.max_steps);
+        graph.set_max_visits_per_node(self.max_visits_per_node);
+
+        for node in &self.nodes {
+            if graph.get_node_tool_registry(node.id).is_some() {
+                return Err(LLMGraphError::SpecError(format!("Duplicate node {}", node.id)));
+            }
+            let agent = factory.build_spec(&node.agent).map_err(|e| match e {
+                LLMGraphError::SpecError(msg) => {
+                    LLMGraphError::SpecError(format!("Node {}: {}", node.id, msg))
+                }
+                e => e,
+            })?;
+            graph.add_node(node.id, agent);
+
+            for name in &node.tools {
+                let (tool, function) = tools.lookup(name).ok_or_else(|| {
+                    LLMGraphError::SpecError(format!("Node {}: unknown tool '{}'", node.id, name))
+                })?;
+                let function = function.clone();
+                graph.register_tool_for_node(node.id, tool.clone(), move |args| function(args))?;
+            }
+        }
+
+        for edge in &self.edges {
+            let added = if edge.directed {
+                graph.add_directed_edge(edge.from, edge.to)
+            } else {
+                graph.add_edge(edge.from, edge.to)
+            };
+            added.map_err(|e| {
+                LLMGraphError::SpecError(format!("Edge {} -> {}: {}", edge.from, edge.to, e))
+            })?;
+        }
+
+        if graph.get_node_tool_registry(self.start).is_none() {
+            return Err(NodeError::NodeNotFound(self.start).into());
+        }
+        Ok(graph)
+    }
+}
@@ -4,8 +4,10 @@
 //! implementing function calling capabilities in AI agents.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use crate::errors::{LLMGraphError, LLMGraphResult, ToolError};
 
 // -----------------------------
 // Tool definitions
@@ -85,6 +87,10 @@ pub struct ToolCall {
     pub call_type: String,
     /// The function call details
     pub function: FunctionCall,
+    /// Opaque signature Gemini attaches to the function calls of thinking
+    /// models; sent back unchanged with the call in later turns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thought_signature: Option<String>,
 }
 
 /// Details of a function call.
@@ -159,8 +165,19 @@ pub trait ToolRegistryTrait: Send + Sync {
     ///
     /// # Returns
     /// * `Ok(Value)` - The result of the tool execution
-    /// * `Err(String)` - An error message if execution fails
-    fn execute_tool(&self, name: &str, arguments: &str) -> Result<Value, String>;
+    /// * `Err(ToolError::ToolNotFound)` - If no tool has this name
+    /// * `Err(ToolError::InvalidArguments)` - If the arguments aren't valid JSON
+    /// * `Err(ToolError::ToolExecutionFailed)` - If the tool itself fails
+    fn execute_tool(&self, name: &str, arguments: &str) -> LLMGraphResult<Value>;
+
+    /// The registry this one delegates to without reporting calls as
+    /// [`GraphEvent`](crate::models::events::GraphEvent)s, for registries the
+    /// graph wraps to report them. Subgraphs use it so calls to outer tools
+    /// are reported once. The default returns `None`.
+    #[doc(hidden)]
+    fn unreported(&self) -> Option<&dyn ToolRegistryTrait> {
+        None
+    }
 }
 
 // -----------------------------
@@ -181,9 +198,10 @@ pub type ToolFunction = dyn Fn(Value) -> Result<Value, String> + Send + Sync;
 ///     Ok(serde_json::json!({"result": "success"}))
 /// });
 /// ```
+#[derive(Clone, Default)]
 pub struct ToolRegistry {
     tools: HashMap<String, Tool>,
-    functions: HashMap<String, Box<ToolFunction>>,
+    functions: HashMap<String, Arc<ToolFunction>>,
 }
 
 impl ToolRegistry {
@@ -216,7 +234,12 @@ impl ToolRegistry {
     {
         let name = tool.function.name.clone();
         self.tools.insert(name.clone(), tool);
-        self.functions.insert(name, Box::new(function));
+        self.functions.insert(name, Arc::new(function));
+    }
+
+    /// Get a registered tool and its implementation by name.
+    pub(crate) fn lookup(&self, name: &str) -> Option<(&Tool, &Arc<ToolFunction>)> {
+        Some((self.tools.get(name)?, self.functions.get(name)?))
     }
 }
 
@@ -225,14 +248,22 @@ impl ToolRegistryTrait for ToolRegistry {
         self.tools.values().cloned().collect()
     }
 
-    fn execute_tool(&self, name: &str, arguments: &str) -> Result<Value, String> {
-        let args: Value = serde_json::from_str(arguments)
-            .map_err(|e| format!("Failed to parse arguments: {}", e))?;
-        if let Some(function) = self.functions.get(name) {
-            function(args)
-        } else {
-            Err(format!("Tool '{}' not found", name))
-        }
+    fn execute_tool(&self, name: &str, arguments: &str) -> LLMGraphResult<Value> {
+        let function = self
+            .functions
+            .get(name)
+            .ok_or_else(|| ToolError::ToolNotFound(name.to_string()))?;
+        let args: Value = serde_json::from_str(arguments).map_err(|e| ToolError::InvalidArguments {
+            name: name.to_string(),
+            error: e.to_string(),
+        })?;
+        function(args).map_err(|error| {
+            ToolError::ToolExecutionFailed {
+                name: name.to_string(),
+                error,
+            }
+            .into()
+        })
     }
 }
 
@@ -242,7 +273,8 @@ impl ToolRegistryTrait for ToolRegistry {
 /// A registry that combines two tool registries.
 ///
 /// The primary registry takes precedence over the secondary registry
-/// when tools have the same name.
+/// when tools have the same name; the secondary registry is only tried for
+/// tools the primary one doesn't have.
 pub struct CombinedToolRegistry<'a> {
     primary: &'a dyn ToolRegistryTrait,
     secondary: &'a dyn ToolRegistryTrait,
@@ -274,10 +306,12 @@ impl<'a> ToolRegistryTrait for CombinedToolRegistry<'a> {
         tools
     }
 
-    fn execute_tool(&self, name: &str, arguments: &str) -> Result<Value, String> {
-        if let Ok(result) = self.primary.execute_tool(name, arguments) {
-            return Ok(result);
+    fn execute_tool(&self, name: &str, arguments: &str) -> LLMGraphResult<Value> {
+        match self.primary.execute_tool(name, arguments) {
+            Err(LLMGraphError::ToolError(ToolError::ToolNotFound(_))) => {
+                self.secondary.execute_tool(name, arguments)
+            }
+            result => result,
         }
-        self.secondary.execute_tool(name, arguments)
     }
 }
\ No newline at end of file
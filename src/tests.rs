@@ -113,29 +113,1235 @@ mod tests {
         
         // Try to add edge with non-existent node
         let result = graph.add_edge(0, 99);
-        assert!(result.is_err(), "Should fail when adding edge to non-existent node");
+        assert!(
+            matches!(result, Err(crate::LLMGraphError::NodeError(crate::errors::NodeError::NodeNotFound(99)))),
+            "Should fail when adding edge to non-existent node"
+        );
+    }
+
+    /// Test graph execution with simple chain
+    #[tokio::test]
+    async fn test_simple_chain_execution() {
+        let mut graph = Graph::new();
+        
+        // Create a simple chain: Agent0 -> Agent1 -> Agent2
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Router", 1)));
+        graph.add_node(1, Box::new(FixedRouteAgent::new("Processor", 2)));
+        graph.add_node(2, Box::new(EchoAgent::new("Final")));
+        
+        // Connect nodes
+        graph.add_edge(0, 1).unwrap();
+        graph.add_edge(1, 2).unwrap();
+        
+        // Execute
+        let result = graph.run(0, "test input").await;
+        
+        assert!(result.contains("Routing"));
+        assert!(result.contains("Echo"));
+        println!("Chain execution result:\n{}", result);
+    }
+
+    /// Test that strict edges only allow hops along declared directed edges
+    #[tokio::test]
+    async fn test_strict_directed_edges() {
+        use crate::errors::{GraphError, LLMGraphError};
+
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Forward", 1)));
+        graph.add_node(1, Box::new(FixedRouteAgent::new("Back", 0)));
+        graph.add_directed_edge(0, 1).unwrap();
+
+        assert!(graph.has_edge(0, 1));
+        assert!(!graph.has_edge(1, 0), "Directed edge should not add the reverse link");
+
+        graph.set_strict_edges(true);
+        match graph.try_run(0, "test input").await {
+            Err(LLMGraphError::GraphError(GraphError::InvalidEdge { from, to })) => {
+                assert_eq!((from, to), (1, 0));
+            }
+            other => panic!("Expected InvalidEdge, got {:?}", other),
+        }
+
+        let result = graph.run(0, "test input").await;
+        assert!(result.contains("Invalid edge from 1 to 0"));
+    }
+
+    /// Test that run_traced records one step per agent invocation
+    #[tokio::test]
+    async fn test_run_traced() {
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Router", 1)));
+        graph.add_node(1, Box::new(EchoAgent::new("Final")));
+        graph.add_edge(0, 1).unwrap();
+
+        let result = graph.run_traced(0, "test input").await;
+        assert!(result.is_success());
+        assert_eq!(result.steps.len(), 2);
+
+        let first = &result.steps[0];
+        assert_eq!(first.node_id, 0);
+        assert_eq!(first.agent_name, "Router");
+        assert_eq!(first.input, "test input");
+        assert_eq!(first.next_id, Some(1));
+        assert!(first.finished_at >= first.started_at);
+
+        let last = &result.steps[1];
+        assert_eq!(last.agent_name, "Final");
+        assert_eq!(last.input, first.output);
+        assert_eq!(last.next_id, None);
+        assert_eq!(result.output, last.output);
+
+        // A missing route target is recorded on the step that chose it
+        graph.add_node(2, Box::new(FixedRouteAgent::new("Lost", 99)));
+        let result = graph.run_traced(2, "test input").await;
+        assert!(!result.is_success());
+        assert_eq!(result.steps.len(), 1);
+        assert!(result.steps[0].error.as_deref().unwrap().contains("Node 99 not found"));
+    }
+
+    /// Test that the step budget and visit limit stop looping graphs
+    #[tokio::test]
+    async fn test_step_limit_and_cycle_protection() {
+        use crate::errors::{AgentError, GraphError, LLMGraphError};
+
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Ping", 1)));
+        graph.add_node(1, Box::new(FixedRouteAgent::new("Pong", 0)));
+        graph.add_edge(0, 1).unwrap();
+
+        graph.set_max_steps(Some(5));
+        let result = graph.run_traced(0, "test input").await;
+        assert_eq!(result.steps.len(), 5, "Partial trace should be kept");
+        assert!(matches!(
+            result.error,
+            Some(LLMGraphError::AgentError(AgentError::MaxIterationsExceeded))
+        ));
+
+        graph.set_max_steps(None);
+        graph.set_max_visits_per_node(Some(2));
+        let result = graph.run_traced(0, "test input").await;
+        assert_eq!(result.steps.len(), 4);
+        match result.error {
+            Some(LLMGraphError::GraphError(GraphError::CycleDetected(path))) => {
+                assert_eq!(path, vec![0, 1, 0]);
+            }
+            other => panic!("Expected CycleDetected, got {:?}", other),
+        }
+    }
+
+    /// Test that one graph can serve several concurrent runs
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_runs() {
+        use std::sync::Arc;
+
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Router", 1)));
+        graph.add_node(1, Box::new(EchoAgent::new("Final")));
+        graph.add_edge(0, 1).unwrap();
+        let graph = Arc::new(graph);
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let graph = graph.clone();
+                tokio::spawn(async move { graph.run(0, &format!("task {}", i)).await })
+            })
+            .collect();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            let output = handle.await.unwrap();
+            assert!(output.contains(&format!("Echo: Routing 'task {}' to node 1", i)));
+        }
+    }
+
+    /// Test static validation of routes, reachability and cycles
+    #[tokio::test]
+    async fn test_graph_validate() {
+        use crate::errors::{GraphError, LLMGraphError, NodeError};
+
+        let graph = Graph::new();
+        assert!(matches!(
+            graph.validate(0).unwrap_err().as_slice(),
+            [LLMGraphError::GraphError(GraphError::EmptyGraph)]
+        ));
+
+        let mut graph = Graph::new();
+        let router = RouterAgent::new()
+            .add_route(RouteRule {
+                pattern: "bug".to_string(),
+                target_node: 1,
+                description: None,
+            })
+            .set_default(7); // Typo: node 7 doesn't exist
+        graph.add_node(0, Box::new(router));
+        graph.add_node(1, Box::new(ValidatorAgent::new().with_success_route(2).with_failure_route(0)));
+        graph.add_node(2, Box::new(EchoAgent::new("Done")));
+        graph.add_node(3, Box::new(EchoAgent::new("Orphan")));
+
+        assert!(matches!(
+            graph.validate(9).unwrap_err().as_slice(),
+            [LLMGraphError::NodeError(NodeError::NodeNotFound(9))]
+        ));
+
+        let problems = graph.validate(0).unwrap_err();
+        assert!(problems.iter().any(|p| matches!(
+            p,
+            LLMGraphError::GraphError(GraphError::InvalidEdge { from: 0, to: 7 })
+        )));
+        assert!(problems.iter().any(|p| matches!(
+            p,
+            LLMGraphError::GraphError(GraphError::DisconnectedGraph(nodes)) if nodes == &vec![3]
+        )));
+        assert!(problems.iter().any(|p| matches!(
+            p,
+            LLMGraphError::GraphError(GraphError::CycleDetected(path)) if path == &vec![0, 1, 0]
+        )));
+
+        // A step guard makes the validator's retry loop acceptable
+        graph.set_max_visits_per_node(Some(3));
+        let problems = graph.validate(0).unwrap_err();
+        assert_eq!(problems.len(), 2);
+
+        // Bidirectional links alone are not cycles
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Router", 1)));
+        graph.add_node(1, Box::new(EchoAgent::new("Final")));
+        graph.add_edge(0, 1).unwrap();
+        assert!(graph.validate(0).is_ok());
+
+        // A loop that passes through bidirectional links is still a cycle
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FixedRouteAgent::new("A", 1)));
+        graph.add_node(1, Box::new(FixedRouteAgent::new("B", 2)));
+        graph.add_node(2, Box::new(FixedRouteAgent::new("C", 0)));
+        graph.add_edge(0, 1).unwrap();
+        graph.add_edge(1, 2).unwrap();
+        graph.add_directed_edge(2, 0).unwrap();
+        let problems = graph.validate(0).unwrap_err();
+        assert!(problems.iter().any(|p| matches!(
+            p,
+            LLMGraphError::GraphError(GraphError::CycleDetected(path)) if path == &vec![0, 1, 2, 0]
+        )));
+    }
+
+    /// Test agent that waits before routing to a specific node
+    pub struct SlowAgent {
+        name: String,
+        target_node: Option<i32>,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl Agent for SlowAgent {
+        async fn run(
+            &mut self,
+            input: &str,
+            _tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ) -> (String, Option<i32>) {
+            tokio::time::sleep(self.delay).await;
+            (format!("{} handled '{}'", self.name, input), self.target_node)
+        }
+
+        fn get_name(&self) -> &str {
+            &self.name
+        }
+    }
+
+    /// Test that fan-out branches run concurrently and merge at the join node
+    #[tokio::test]
+    async fn test_fan_out_fan_in() {
+        use crate::agents::parallel::CombineStrategy;
+
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Start", 10)));
+        for (id, name) in [(1, "Research"), (2, "Pricing")] {
+            graph.add_node(id, Box::new(SlowAgent {
+                name: name.to_string(),
+                target_node: Some(3),
+                delay: Duration::from_millis(200),
+            }));
+        }
+        graph.add_node(3, Box::new(EchoAgent::new("Writer")));
+        graph.add_fan_out(10, vec![1, 2], 3, CombineStrategy::JsonArray).unwrap();
+        graph.add_edge(0, 10).unwrap();
+
+        assert!(graph.add_fan_out(11, vec![1, 99], 3, CombineStrategy::Concatenate).is_err());
+        assert!(graph.validate(0).is_ok());
+
+        let started = std::time::Instant::now();
+        let result = graph.run_traced(0, "task").await;
+        assert!(started.elapsed() < Duration::from_millis(390), "Branches should run concurrently");
+        assert!(result.is_success(), "{:?}", result.error);
+
+        let names: Vec<&str> = result.steps.iter().map(|s| s.agent_name.as_str()).collect();
+        assert_eq!(names, vec!["Start", "Research", "Pricing", "FanOut", "Writer"]);
+
+        let merged: Vec<String> = serde_json::from_str(&result.steps[3].output).unwrap();
+        assert_eq!(merged.len(), 2);
+        assert!(merged[0].starts_with("Research handled"));
+        assert_eq!(result.steps[4].input, result.steps[3].output);
+    }
+
+    /// Test agent that records a field in the run state
+    pub struct TriageAgent;
+
+    #[async_trait]
+    impl Agent for TriageAgent {
+        async fn run(
+            &mut self,
+            input: &str,
+            _tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ) -> (String, Option<i32>) {
+            (input.to_string(), None)
+        }
+
+        async fn run_with_context(
+            &mut self,
+            input: &str,
+            _tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+            ctx: &crate::RunContext,
+        ) -> (String, Option<i32>) {
+            match ctx.get("ticket_id") {
+                Some(ticket_id) => {
+                    ctx.set("priority", serde_json::json!("high"));
+                    (format!("Ticket {} triaged", ticket_id), None)
+                }
+                None => {
+                    ctx.set("ticket_id", serde_json::json!(4711));
+                    (format!("Opened ticket for '{}'", input), Some(1))
+                }
+            }
+        }
+
+        fn get_name(&self) -> &str {
+            "Triage"
+        }
+    }
+
+    /// Test that the run state is shared across nodes and returned in the result
+    #[tokio::test]
+    async fn test_run_state() {
+        use crate::RunOptions;
+
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(TriageAgent));
+        graph.add_node(1, Box::new(RetryAgent::new(Box::new(TriageAgent))));
+        graph.add_edge(0, 1).unwrap();
+
+        let mut initial = serde_json::Map::new();
+        initial.insert("customer".to_string(), serde_json::json!("ACME"));
+        let result = graph
+            .run_with_options(0, "printer on fire", RunOptions::new().with_state(initial))
+            .await;
+
+        assert!(result.is_success());
+        assert_eq!(result.output, "Ticket 4711 triaged");
+        assert_eq!(result.state["customer"], "ACME");
+        assert_eq!(result.state["ticket_id"], 4711);
+        assert_eq!(result.state["priority"], "high");
+    }
+
+    /// Test that a failed run can be resumed from its last checkpoint
+    #[tokio::test]
+    async fn test_checkpoint_and_resume() {
+        use crate::models::checkpoint::{CheckpointStore, JsonFileCheckpointStore};
+        use crate::RunOptions;
+        use std::sync::Arc;
+
+        let dir = std::env::temp_dir().join(format!("llmgraph-checkpoints-{}", std::process::id()));
+        let store = Arc::new(JsonFileCheckpointStore::new(&dir));
+
+        let build_graph = |second: Box<dyn Agent>| {
+            let mut graph = Graph::new();
+            graph.set_checkpoint_store(store.clone());
+            let counter = StatefulAgent::new("Counter")
+                .with_processor(Box::new(|input, state| {
+                    (format!("Counted {} ({})", input, state.execution_count), Some(1))
+                }));
+            graph.add_node(0, Box::new(counter));
+            graph.add_node(1, second);
+            graph.add_node(2, Box::new(EchoAgent::new("Final")));
+            graph
+        };
+
+        // The first attempt dies at node 1 (it routes to a missing node)
+        let graph = build_graph(Box::new(FixedRouteAgent::new("Broken", 5)));
+        let options = RunOptions::new().with_run_id("resume-test");
+        let result = graph.run_with_options(0, "order", options).await;
+        assert!(!result.is_success());
+        assert_eq!(result.run_id, "resume-test");
+
+        let checkpoint = store.load("resume-test").await.unwrap().unwrap();
+        assert_eq!(checkpoint.next_node, Some(1));
+        assert_eq!(checkpoint.pending_input, "Counted order (1)");
+        assert_eq!(checkpoint.steps.len(), 1);
+        assert!(checkpoint.agent_states[&0].contains("\"execution_count\":1"));
+
+        // A new graph with a fixed node 1 continues after the last completed step
+        let graph = build_graph(Box::new(FixedRouteAgent::new("Fixed", 2)));
+        let result = graph.resume("resume-test").await.unwrap();
+        assert!(result.is_success(), "{:?}", result.error);
+        let names: Vec<&str> = result.steps.iter().map(|s| s.agent_name.as_str()).collect();
+        assert_eq!(names, vec!["Counter", "Fixed", "Final"]);
+        assert_eq!(result.output, "Echo: Routing 'Counted order (1)' to node 2");
+
+        let finished = store.load("resume-test").await.unwrap().unwrap();
+        assert_eq!(finished.next_node, None);
+        assert!(graph.resume("unknown-run").await.is_err());
+
+        // Run IDs the store can't use fail the run before any agent runs
+        let options = RunOptions::new().with_run_id("../escape");
+        let result = graph.run_with_options(0, "order", options).await;
+        assert!(matches!(result.error, Some(crate::LLMGraphError::CheckpointError(_))));
+        assert!(result.steps.is_empty());
+
+        store.delete("resume-test").await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Test agent that asks a human before answering
+    pub struct ApprovalAgent;
+
+    #[async_trait]
+    impl Agent for ApprovalAgent {
+        async fn run(
+            &mut self,
+            input: &str,
+            _tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ) -> (String, Option<i32>) {
+            (input.to_string(), None)
+        }
+
+        async fn run_with_context(
+            &mut self,
+            input: &str,
+            _tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+            ctx: &crate::RunContext,
+        ) -> (String, Option<i32>) {
+            match ctx.human_input() {
+                Some(answer) => (format!("Refund for '{}': {}", input, answer), None),
+                None => {
+                    ctx.interrupt("Approve the refund?");
+                    ("Waiting for approval".to_string(), None)
+                }
+            }
+        }
+
+        fn get_name(&self) -> &str {
+            "Approval"
+        }
+    }
+
+    /// Test that interrupt points and agents pause a run until resumed with human input
+    #[tokio::test]
+    async fn test_human_in_the_loop_interrupts() {
+        use crate::models::checkpoint::InMemoryCheckpointStore;
+        use crate::models::run::RunStatus;
+        use crate::LLMGraphError;
+        use std::sync::Arc;
+
+        let mut graph = Graph::new();
+        graph.set_checkpoint_store(Arc::new(InMemoryCheckpointStore::new()));
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Drafter", 1)));
+        graph.add_node(1, Box::new(FixedRouteAgent::new("Publisher", 2)));
+        graph.add_node(2, Box::new(ApprovalAgent));
+        graph.set_interrupt(1, "Publish this draft?").unwrap();
+        assert!(graph.set_interrupt(9, "Missing node").is_err());
+
+        // The interrupt point pauses before node 1 runs
+        let result = graph.run_traced(0, "draft").await;
+        assert!(result.is_success());
+        let run_id = result.run_id.clone();
+        assert_eq!(result.status, RunStatus::Paused {
+            run_id: run_id.clone(),
+            node: 1,
+            prompt: "Publish this draft?".to_string(),
+        });
+        assert_eq!(result.steps.len(), 1);
+
+        // Node 1 runs with its original input; node 2 then pauses by itself
+        let result = graph.resume_with(&run_id, "yes").await.unwrap();
+        assert!(result.is_paused());
+        assert_eq!(result.steps[1].input, "Routing 'draft' to node 1");
+        match &result.status {
+            RunStatus::Paused { node, prompt, .. } => {
+                assert_eq!(*node, 2);
+                assert_eq!(prompt, "Approve the refund?");
+            }
+            other => panic!("Expected Paused, got {:?}", other),
+        }
+
+        // The agent runs again and sees the human input
+        let result = graph.resume_with(&run_id, "approved").await.unwrap();
+        assert_eq!(result.status, RunStatus::Completed);
+        assert_eq!(
+            result.output,
+            "Refund for 'Routing 'Routing 'draft' to node 1' to node 2': approved"
+        );
+
+        assert!(graph.resume_with(&run_id, "again").await.is_err(), "Finished runs are not paused");
+
+        // Without a checkpoint store the run could never resume, so it fails
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Drafter", 1)));
+        graph.add_node(1, Box::new(ApprovalAgent));
+        let result = graph.run_traced(0, "draft").await;
+        assert_eq!(result.status, RunStatus::Failed);
+        assert!(matches!(result.error, Some(LLMGraphError::CheckpointError(_))));
+        graph.set_interrupt(0, "Start?").unwrap();
+        let result = graph.run_traced(0, "draft").await;
+        assert_eq!(result.status, RunStatus::Failed);
+        assert!(result.steps.is_empty());
+    }
+
+    /// Test agent counting its runs in a counter shared with the test
+    struct CountingAgent {
+        count: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Agent for CountingAgent {
+        async fn run(&mut self, input: &str, _: &(dyn ToolRegistryTrait + Send + Sync)) -> (String, Option<i32>) {
+            let count = self.count.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            (format!("{} #{}", input, count), None)
+        }
+
+        fn get_name(&self) -> &str {
+            "Counter"
+        }
+
+        fn snapshot(&self) -> Option<String> {
+            Some(self.count.load(std::sync::atomic::Ordering::SeqCst).to_string())
+        }
+
+        fn restore(&mut self, snapshot: &str) -> crate::LLMGraphResult<()> {
+            let count = snapshot.parse().map_err(|_| crate::LLMGraphError::CheckpointError(snapshot.to_string()))?;
+            self.count.store(count, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Test that rejected resumes leave the agents' state alone
+    #[tokio::test]
+    async fn test_rejected_resume_keeps_agent_state() {
+        use crate::models::checkpoint::InMemoryCheckpointStore;
+        use crate::LLMGraphError;
+        use std::sync::Arc;
+
+        let count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let observer = CountingAgent { count: count.clone() };
+        let mut graph = Graph::new();
+        graph.set_checkpoint_store(Arc::new(InMemoryCheckpointStore::new()));
+        graph.add_node(0, Box::new(CountingAgent { count }));
+
+        let finished = graph.run_traced(0, "first").await;
+        assert!(finished.is_success());
+        assert_eq!(graph.run_traced(0, "second").await.output, "second #2");
+
+        // The finished run's checkpoint holds count 1, which must not be restored
+        for result in [
+            graph.resume_with(&finished.run_id, "approved").await,
+            graph.resume(&finished.run_id).await,
+        ] {
+            match result {
+                Err(LLMGraphError::CheckpointError(msg)) => assert!(msg.contains("already finished"), "{}", msg),
+                other => panic!("Expected a checkpoint error, got {:?}", other),
+            }
+        }
+        assert_eq!(observer.snapshot().as_deref(), Some("2"));
+    }
+
+    /// Agent that looks up a word with a tool and reports its output word by word
+    struct LookupAgent;
+
+    #[async_trait]
+    impl Agent for LookupAgent {
+        async fn run(&mut self, input: &str, tool_registry: &(dyn ToolRegistryTrait + Send + Sync)) -> (String, Option<i32>) {
+            let ctx = crate::RunContext::new(0, Default::default());
+            self.run_with_context(input, tool_registry, &ctx).await
+        }
+
+        async fn run_with_context(
+            &mut self,
+            input: &str,
+            tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+            ctx: &crate::RunContext,
+        ) -> (String, Option<i32>) {
+            let arguments = serde_json::json!({ "word": input }).to_string();
+            let definition = tool_registry
+                .execute_tool("lookup", &arguments)
+                .unwrap_or_default();
+            let output = format!("{} means {}", input, definition.as_str().unwrap_or("nothing"));
+            for word in output.split_inclusive(' ') {
+                ctx.emit_token(word);
+            }
+            (output, None)
+        }
+
+        fn get_name(&self) -> &str {
+            "Lookup"
+        }
+    }
+
+    /// The tool used by LookupAgent
+    fn lookup_tool() -> Tool {
+        Tool {
+            tool_type: "function".to_string(),
+            function: Function {
+                name: "lookup".to_string(),
+                description: "Looks up a word".to_string(),
+                parameters: Parameters {
+                    param_type: "object".to_string(),
+                    properties: HashMap::new(),
+                    required: Vec::new(),
+                },
+            },
+        }
+    }
+
+    /// Test that run_stream reports node, tool and token events in order
+    #[tokio::test]
+    async fn test_run_stream_events() {
+        use crate::models::events::GraphEvent;
+        use crate::RunOptions;
+        use futures::StreamExt;
+
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Start", 1)));
+        graph.add_node(1, Box::new(LookupAgent));
+        graph.add_edge(0, 1).unwrap();
+        graph.register_tool(lookup_tool(), |_| Ok(serde_json::json!("a graph")));
+
+        let events: Vec<GraphEvent> = graph
+            .run_stream(0, "llm", RunOptions::new())
+            .collect()
+            .await;
+        let mut kinds: Vec<&str> = events
+            .iter()
+            .map(|event| match event {
+                GraphEvent::NodeStarted { .. } => "started",
+                GraphEvent::NodeFinished(_) => "finished",
+                GraphEvent::ToolCalled { .. } => "tool",
+                GraphEvent::ToolResult { .. } => "result",
+                GraphEvent::TokenDelta { .. } => "token",
+                GraphEvent::RunFinished(_) => "done",
+                GraphEvent::RunFailed(_) => "failed",
+            })
+            .collect();
+        kinds.dedup();
+        assert_eq!(
+            kinds,
+            vec!["started", "finished", "started", "tool", "result", "token", "finished", "done"]
+        );
+
+        let tokens: String = events
+            .iter()
+            .filter_map(|event| match event {
+                GraphEvent::TokenDelta { delta, .. } => Some(delta.as_str()),
+                _ => None,
+            })
+            .collect();
+        match events.last() {
+            Some(GraphEvent::RunFinished(result)) => assert_eq!(result.output, tokens),
+            other => panic!("Expected RunFinished, got {:?}", other),
+        }
+
+        // A failing run ends with RunFailed
+        let events: Vec<GraphEvent> = graph.run_stream(7, "llm", RunOptions::new()).collect().await;
+        assert_eq!(events.len(), 1);
+        assert!(matches!(&events[0], GraphEvent::RunFailed(result) if result.error.is_some()));
+
+        // Outer tools called from a subgraph are reported once, by the inner node
+        let mut inner = Graph::new();
+        inner.add_node(0, Box::new(FixedRouteAgent::new("Start", 1)));
+        inner.add_node(1, Box::new(LookupAgent));
+        inner.add_edge(0, 1).unwrap();
+        let mut outer = Graph::new();
+        outer.add_node(5, Box::new(SubgraphAgent::new("Writing", std::sync::Arc::new(inner), 0)));
+        outer.register_tool(lookup_tool(), |_| Ok(serde_json::json!("an outer tool")));
+        let events: Vec<GraphEvent> = outer.run_stream(5, "llm", RunOptions::new()).collect().await;
+        let tool_events: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                GraphEvent::ToolCalled { node_id, name, .. } => Some((*node_id, name.as_str(), None)),
+                GraphEvent::ToolResult { node_id, name, result } => Some((*node_id, name.as_str(), Some(result))),
+                _ => None,
+            })
+            .collect();
+        // Exactly one ToolCalled and one ToolResult for the single call
+        assert_eq!(tool_events.len(), 2, "{:?}", tool_events);
+        assert_eq!((tool_events[0].0, tool_events[0].1, tool_events[0].2), (1, "lookup", None));
+        assert_eq!((tool_events[1].0, tool_events[1].1), (1, "lookup"));
+        assert_eq!(tool_events[1].2, Some(&Ok(serde_json::json!("an outer tool"))));
+    }
+
+    /// Test that a graph nested as an agent runs as one node with a nested trace
+    #[tokio::test]
+    async fn test_subgraph_agent() {
+        use std::sync::Arc;
+
+        let mut inner = Graph::new();
+        inner.add_node(0, Box::new(FixedRouteAgent::new("Research", 1)));
+        inner.add_node(1, Box::new(LookupAgent));
+        inner.add_edge(0, 1).unwrap();
+        let inner = Arc::new(inner);
+
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(SubgraphAgent::new("Writing", inner.clone(), 0).with_next(1)));
+        graph.add_node(1, Box::new(EchoAgent::new("Publisher")));
+        graph.add_edge(0, 1).unwrap();
+        graph.register_tool(lookup_tool(), |_| Ok(serde_json::json!("an outer tool")));
+        assert!(graph.validate(0).is_ok());
+
+        // The inner graph falls back to the outer tools, then outer routing applies
+        let result = graph.run_traced(0, "word").await;
+        assert!(result.is_success());
+        assert_eq!(
+            result.output,
+            "Echo: Routing 'word' to node 1 means an outer tool"
+        );
+        assert_eq!(result.steps.len(), 2);
+        let names: Vec<&str> = result.steps[0]
+            .substeps
+            .iter()
+            .map(|step| step.agent_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Research", "Lookup"]);
+        assert_eq!(result.steps[0].output, result.steps[0].substeps[1].output);
+        assert!(result.steps[1].substeps.is_empty());
+
+        // Tools of the inner graph take precedence over the outer ones
+        let mut inner = Graph::new();
+        inner.add_node(0, Box::new(LookupAgent));
+        inner.register_tool(lookup_tool(), |_| Ok(serde_json::json!("an inner tool")));
+        graph.add_node(0, Box::new(SubgraphAgent::new("Lookup", Arc::new(inner), 0)));
+        let result = graph.run_traced(0, "word").await;
+        assert_eq!(result.output, "word means an inner tool");
+
+        // Failures inside the subgraph end the outer chain
+        graph.add_node(0, Box::new(SubgraphAgent::new("Broken", Arc::new(Graph::new()), 5).with_next(1)));
+        let result = graph.run_traced(0, "word").await;
+        assert_eq!(result.steps.len(), 1);
+        assert!(result.output.starts_with("Subgraph 'Broken' failed"));
+    }
+
+    /// Test building a graph from a spec with custom and built-in agent types
+    #[tokio::test]
+    async fn test_graph_spec() {
+        use crate::agents::factory::AgentSpec;
+        use crate::models::spec::GraphSpec;
+        use crate::models::tools::ToolRegistry;
+        use crate::LLMGraphError;
+
+        let mut factory = AgentFactory::new();
+        factory.register("lookup", |_, _| Ok(Box::new(LookupAgent) as Box<dyn Agent>));
+        let mut tools = ToolRegistry::new();
+        tools.register_tool(lookup_tool(), |_| Ok(serde_json::json!("a graph")));
+
+        let spec = GraphSpec::from_json(GRAPH_SPEC_JSON).unwrap();
+        let graph = spec.build(&factory, &tools).unwrap();
+        assert!(graph.validate(spec.start).is_ok());
+        assert_eq!(graph.get_node_tool_registry(1).unwrap().get_tools().len(), 1);
+        assert!(graph.get_node_tool_registry(2).unwrap().get_tools().is_empty());
+
+        let result = graph.run_traced(spec.start, "define graph").await;
+        assert!(result.is_success());
+        let names: Vec<&str> = result.steps.iter().map(|step| step.agent_name.as_str()).collect();
+        assert_eq!(names, vec!["Triage", "Lookup"]);
+        assert!(result.output.ends_with("means a graph"));
+
+        // The same spec as JSON builds the same graph
+        let json = serde_json::to_string(&spec).unwrap();
+        let graph = GraphSpec::from_json(&json).unwrap().build(&factory, &tools).unwrap();
+        assert!(graph.validate(0).is_ok());
+
+        // Builder options of wrapping agents are deserializable too
+        let retry: AgentSpec = serde_json::from_value(serde_json::json!({
+            "type": "retry",
+            "config": {
+                "agent": {"type": "summarizer", "config": {"style": "key_points"}},
+                "max_retries": 2,
+                "strategy": {"exponential_backoff": {"base": 100, "max": 2000, "multiplier": 2.0}}
+            }
+        }))
+        .unwrap();
+        let agent = factory.build_spec(&retry).unwrap();
+        assert_eq!(agent.get_name(), "Retry[Summarizer]");
+        let strategy: RetryStrategy = serde_json::from_str(r#"{"fixed": 250}"#).unwrap();
+        assert!(matches!(strategy, RetryStrategy::Fixed(delay) if delay == Duration::from_millis(250)));
+
+        // Invalid specs are reported, not panicked on
+        let invalid = |json: &str| match GraphSpec::from_json(json).and_then(|spec| spec.build(&factory, &tools)) {
+            Err(LLMGraphError::SpecError(msg)) => msg,
+            Err(e) => panic!("Expected a spec error, got {}", e),
+            Ok(_) => panic!("Expected {} to be rejected", json),
+        };
+        assert!(invalid(r#"{"start": 0, "nodes": [{"id": 0, "type": "planner"}]}"#).contains("Unknown agent type 'planner'"));
+        assert!(invalid(r#"{"start": 0, "nodes": [{"id": 0, "type": "lookup", "tools": ["search"]}]}"#).contains("unknown tool 'search'"));
+        assert!(invalid(r#"{"start": 0, "nodes": [{"id": 0, "type": "router", "config": {"route": []}}]}"#).contains("unknown field"));
+        let msg = invalid(r#"{"start": 0, "nodes": [{"id": 0, "type": "router", "config": {"routes": [{"pattern": "refund(", "target_node": 1}]}}]}"#);
+        assert!(msg.starts_with("Node 0: Invalid pattern for route to node 1"), "{}", msg);
+        assert!(invalid(r#"{"start": 0, "nodes": [{"id": 0, "type": "lookup"}], "edges": [{"from": 0, "to": 1}]}"#).contains("Edge 0 -> 1"));
+        assert!(invalid(r#"{"nodes": []}"#).contains("start"));
+    }
+
+    const GRAPH_SPEC_JSON: &str = r#"{
+        "start": 0,
+        "strict_edges": true,
+        "max_steps": 10,
+        "nodes": [
+            {"id": 0, "type": "router", "config": {
+                "name": "Triage",
+                "routes": [{"pattern": "define", "target_node": 1}],
+                "default": 2
+            }},
+            {"id": 1, "type": "lookup", "tools": ["lookup"]},
+            {"id": 2, "type": "summarizer", "config": {"style": "bullets", "max_length": 100}}
+        ],
+        "edges": [
+            {"from": 0, "to": 1, "directed": true},
+            {"from": 0, "to": 2, "directed": true}
+        ]
+    }"#;
+
+    /// Test that a YAML spec parses to the same spec as its JSON form
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_graph_spec_yaml() {
+        use crate::models::spec::GraphSpec;
+        use crate::LLMGraphError;
+
+        let yaml = r#"
+start: 0
+strict_edges: true
+max_steps: 10
+nodes:
+  - id: 0
+    type: router
+    config:
+      name: Triage
+      routes:
+        - { pattern: "define", target_node: 1 }
+      default: 2
+  - id: 1
+    type: lookup
+    tools: [lookup]
+  - id: 2
+    type: summarizer
+    config: { style: bullets, max_length: 100 }
+edges:
+  - { from: 0, to: 1, directed: true }
+  - { from: 0, to: 2, directed: true }
+"#;
+        let spec = GraphSpec::from_yaml(yaml).unwrap();
+        let json = GraphSpec::from_json(GRAPH_SPEC_JSON).unwrap();
+        assert_eq!(
+            serde_json::to_value(&spec).unwrap(),
+            serde_json::to_value(&json).unwrap()
+        );
+        assert!(matches!(GraphSpec::from_yaml("nodes: []"), Err(LLMGraphError::SpecError(_))));
+    }
+
+    /// Test DOT and Mermaid export, with and without a trace overlay
+    #[tokio::test]
+    async fn test_graph_export() {
+        use crate::agents::parallel::CombineStrategy;
+
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Start", 1)));
+        graph.add_node(1, Box::new(EchoAgent::new("Writer")));
+        graph.add_node(2, Box::new(FixedRouteAgent::new("Archive", 0)));
+        graph.add_edge(0, 1).unwrap();
+        graph.add_directed_edge(1, 2).unwrap();
+        graph
+            .register_tool_for_node(1, lookup_tool(), |_| Ok(serde_json::json!("a graph")))
+            .unwrap();
+
+        assert_eq!(
+            graph.to_dot(),
+            "digraph llmgraph {\n    rankdir=LR;\n    node [shape=box];\n\
+             \x20   0 [label=\"0: Start\"];\n\
+             \x20   1 [label=\"1: Writer\\ntools: lookup\"];\n\
+             \x20   2 [label=\"2: Archive\"];\n\
+             \x20   0 -> 1 [dir=both];\n\
+             \x20   1 -> 2;\n}\n"
+        );
+        assert_eq!(
+            graph.to_mermaid(),
+            "flowchart LR\n    n0[\"0: Start\"]\n    n1[\"1: Writer<br/>tools: lookup\"]\n    n2[\"2: Archive\"]\n\
+             \x20   n0 <--> n1\n    n1 --> n2\n"
+        );
+
+        // 2 -> 0 has no edge, so the trace overlay draws it dotted
+        let result = graph.run_traced(2, "report").await;
+        assert!(result.is_success());
+        let dot = graph.to_dot_with_trace(&result.steps);
+        assert!(dot.contains("0 [label=\"0: Start\\n1 visit, "));
+        assert!(dot.contains("0 -> 1 [dir=both, label=\"1x\", penwidth=2];"));
+        assert!(dot.contains("1 -> 2;"));
+        assert!(dot.contains("2 -> 0 [style=dotted, label=\"1x\", penwidth=2];"));
+        let mermaid = graph.to_mermaid_with_trace(&result.steps);
+        assert!(mermaid.contains("n0 <-->|1x| n1"));
+        assert!(mermaid.contains("n2 -.->|1x| n0"));
+        assert!(mermaid.contains("class n0,n1,n2 visited"));
+
+        // Fan-outs are diamonds with a dashed edge to their join node
+        graph.add_fan_out(3, vec![0, 2], 1, CombineStrategy::Concatenate).unwrap();
+        assert!(graph.to_dot().contains("3 [label=\"3: FanOut\", shape=diamond];"));
+        assert!(graph.to_dot().contains("3 -> 1 [style=dashed, label=\"join\"];"));
+        assert!(graph.to_mermaid().contains("n3{\"3: FanOut\"}"));
+        assert!(graph.to_mermaid().contains("n3 -.->|join| n1"));
+    }
+
+    /// Agent that calls an LLM endpoint and returns the raw response
+    struct HttpAgent {
+        url: String,
+    }
+
+    #[async_trait]
+    impl Agent for HttpAgent {
+        async fn run(&mut self, _input: &str, _tool_registry: &(dyn ToolRegistryTrait + Send + Sync)) -> (String, Option<i32>) {
+            let response = crate::generate::generate::generate_full_response(
+                self.url.clone(),
+                TEST_API_KEY.to_string(),
+                TEST_MODEL.to_string(),
+                0.0,
+                Vec::new(),
+                None,
+            )
+            .await;
+            match response {
+                Ok(_) => ("Got a response".to_string(), None),
+                Err(e) => (e.to_string(), None),
+            }
+        }
+
+        fn get_name(&self) -> &str {
+            "Http"
+        }
+    }
+
+    /// Test per-node timeouts, run deadlines and cancellation of in-flight requests
+    #[tokio::test]
+    async fn test_cancellation_and_deadlines() {
+        use crate::errors::{GraphError, NodeError};
+        use crate::models::run::CancellationToken;
+        use crate::{LLMGraphError, RunOptions};
+        use std::time::Instant;
+        use tokio::io::AsyncReadExt;
+
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Start", 1)));
+        graph.add_node(1, Box::new(SlowAgent {
+            name: "Slow".to_string(),
+            target_node: None,
+            delay: Duration::from_millis(300),
+        }));
+        graph.add_edge(0, 1).unwrap();
+
+        // A node timeout drops the agent and keeps the partial trace
+        graph.set_node_timeout(1, Duration::from_millis(50)).unwrap();
+        assert!(graph.set_node_timeout(9, Duration::from_millis(50)).is_err());
+        let started = Instant::now();
+        let result = graph.run_traced(0, "task").await;
+        assert!(started.elapsed() < Duration::from_millis(250));
+        assert!(matches!(
+            result.error,
+            Some(LLMGraphError::NodeError(NodeError::Timeout { node_id: 1, .. }))
+        ));
+        assert_eq!(result.steps.len(), 2);
+        assert!(result.steps[1].error.as_ref().unwrap().contains("timed out"));
+
+        // The run deadline applies across nodes
+        graph.set_node_timeout(1, Duration::from_secs(10)).unwrap();
+        let options = RunOptions::new().with_timeout(Duration::from_millis(50));
+        let result = graph.run_with_options(0, "task", options).await;
+        assert!(matches!(result.error, Some(LLMGraphError::GraphError(GraphError::DeadlineExceeded))));
+        let options = RunOptions::new().with_timeout(Duration::ZERO);
+        let result = graph.run_with_options(0, "task", options).await;
+        assert!(result.steps.is_empty(), "Overdue runs don't start another node");
+
+        // Cancelling aborts an in-flight HTTP request to a server that never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0; 4096];
+            // Read the request, then wait for the client to hang up
+            while socket.read(&mut buffer).await.unwrap_or(0) > 0 {}
+        });
+
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(HttpAgent { url }));
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            canceller.cancel();
+        });
+        let options = RunOptions::new().with_cancellation(token);
+        let result = graph.run_with_options(0, "task", options).await;
+        assert!(matches!(result.error, Some(LLMGraphError::GraphError(GraphError::Cancelled))));
+        assert_eq!(result.steps.len(), 1);
+        tokio::time::timeout(Duration::from_secs(2), server)
+            .await
+            .expect("The request should be aborted")
+            .unwrap();
+    }
+
+    /// Test that resumed runs honour cancellation and stream their events
+    #[tokio::test]
+    async fn test_resume_with_options() {
+        use crate::errors::GraphError;
+        use crate::models::checkpoint::InMemoryCheckpointStore;
+        use crate::models::events::GraphEvent;
+        use crate::models::run::CancellationToken;
+        use crate::{LLMGraphError, RunOptions};
+        use futures::StreamExt;
+        use std::sync::Arc;
+
+        let mut graph = Graph::new();
+        graph.set_checkpoint_store(Arc::new(InMemoryCheckpointStore::new()));
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Drafter", 1)));
+        graph.add_node(1, Box::new(SlowAgent {
+            name: "Publisher".to_string(),
+            target_node: None,
+            delay: Duration::from_secs(5),
+        }));
+        graph.set_interrupt(1, "Publish?").unwrap();
+
+        let result = graph.run_traced(0, "draft").await;
+        assert!(result.is_paused());
+        let run_id = result.run_id.clone();
+
+        // Cancelling stops the resumed run while node 1 is still working
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+        let options = RunOptions::new().with_cancellation(token);
+        let result = tokio::time::timeout(
+            Duration::from_secs(2),
+            graph.resume_with_options(&run_id, Some("yes"), options),
+        )
+        .await
+        .expect("The resumed run should be cancelled")
+        .unwrap();
+        assert!(matches!(result.error, Some(LLMGraphError::GraphError(GraphError::Cancelled))));
+
+        // Resumed runs stream their events
+        let mut graph = Graph::new();
+        graph.set_checkpoint_store(Arc::new(InMemoryCheckpointStore::new()));
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Drafter", 1)));
+        graph.add_node(1, Box::new(EchoAgent::new("Published")));
+        graph.set_interrupt(1, "Publish?").unwrap();
+        let run_id = graph.run_traced(0, "draft").await.run_id;
+        let events: Vec<GraphEvent> = graph
+            .resume_stream(&run_id, Some("yes"), RunOptions::new())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(matches!(&events[0], GraphEvent::NodeStarted { node_id: 1, .. }));
+        assert!(matches!(events.last(), Some(GraphEvent::RunFinished(result)) if result.steps.len() == 2));
+        assert!(graph.resume_stream("unknown-run", None, RunOptions::new()).await.is_err());
+    }
+
+    /// Middleware that records every hook call
+    struct RecordingMiddleware {
+        name: &'static str,
+        calls: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl crate::models::middleware::Middleware for RecordingMiddleware {
+        async fn before(&self, node: i32, _input: &mut String, _ctx: &crate::RunContext) -> crate::LLMGraphResult<()> {
+            self.calls.lock().unwrap().push(format!("{} before {}", self.name, node));
+            Ok(())
+        }
+
+        async fn after(
+            &self,
+            node: i32,
+            _output: &mut String,
+            _next: &mut Option<i32>,
+            _ctx: &crate::RunContext,
+        ) -> crate::LLMGraphResult<()> {
+            self.calls.lock().unwrap().push(format!("{} after {}", self.name, node));
+            Ok(())
+        }
+    }
+
+    /// Middleware that redacts inputs, reroutes and guards outputs
+    struct GuardMiddleware;
+
+    #[async_trait]
+    impl crate::models::middleware::Middleware for GuardMiddleware {
+        async fn before(&self, _node: i32, input: &mut String, _ctx: &crate::RunContext) -> crate::LLMGraphResult<()> {
+            *input = input.replace("secret", "[redacted]");
+            Ok(())
+        }
+
+        async fn after(
+            &self,
+            _node: i32,
+            output: &mut String,
+            next: &mut Option<i32>,
+            _ctx: &crate::RunContext,
+        ) -> crate::LLMGraphResult<()> {
+            if output.contains("forbidden") {
+                return Err(crate::errors::GraphError::Stopped("Forbidden output".to_string()).into());
+            }
+            if output.contains("escalate") {
+                *next = Some(2);
+            }
+            Ok(())
+        }
+    }
+
+    /// Middleware that sends every node on to a fixed node
+    struct RerouteMiddleware(i32);
+
+    #[async_trait]
+    impl crate::models::middleware::Middleware for RerouteMiddleware {
+        async fn after(
+            &self,
+            _node: i32,
+            _output: &mut String,
+            next: &mut Option<i32>,
+            _ctx: &crate::RunContext,
+        ) -> crate::LLMGraphResult<()> {
+            *next = Some(self.0);
+            Ok(())
+        }
+    }
+
+    /// Test that middleware wraps agents, can rewrite inputs, reroute and stop runs
+    #[tokio::test]
+    async fn test_middleware() {
+        use crate::errors::GraphError;
+        use crate::LLMGraphError;
+        use std::sync::{Arc, Mutex};
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FixedRouteAgent::new("Start", 1)));
+        graph.add_node(1, Box::new(EchoAgent::new("Support")));
+        graph.add_node(2, Box::new(EchoAgent::new("Escalation")));
+        graph.add_middleware(Arc::new(RecordingMiddleware { name: "outer", calls: calls.clone() }));
+        graph
+            .add_node_middleware(1, Arc::new(RecordingMiddleware { name: "inner", calls: calls.clone() }))
+            .unwrap();
+        graph.add_node_middleware(1, Arc::new(GuardMiddleware)).unwrap();
+        assert!(graph.add_node_middleware(9, Arc::new(GuardMiddleware)).is_err());
+
+        // Global middleware is outermost; node middleware only wraps its node
+        let result = graph.run_traced(0, "my secret").await;
+        assert!(result.is_success());
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec!["outer before 0", "outer after 0", "outer before 1", "inner before 1", "inner after 1", "outer after 1"]
+        );
+        assert_eq!(result.steps[1].input, "Routing 'my [redacted]' to node 1");
+        assert_eq!(result.output, "Echo: Routing 'my [redacted]' to node 1");
+
+        // After hooks can override the route
+        let result = graph.run_traced(0, "please escalate").await;
+        assert_eq!(result.steps.len(), 3);
+        assert_eq!(result.steps[1].next_id, Some(2));
+        assert_eq!(result.steps[2].agent_name, "Escalation");
+
+        // And stop the run with a typed error
+        let result = graph.run_traced(0, "something forbidden").await;
+        assert!(matches!(result.error, Some(LLMGraphError::GraphError(GraphError::Stopped(_)))));
+        assert_eq!(result.steps.len(), 2);
+        assert_eq!(result.steps[1].error.as_deref(), Some("Graph error: Run stopped: Forbidden output"));
+
+        // Rerouting doesn't turn a failure into a successful hop
+        let mut graph = Graph::new();
+        graph.add_node(0, Box::new(FailingAgent::new(1)));
+        graph.add_node(1, Box::new(EchoAgent::new("Support")));
+        graph.add_node_middleware(0, Arc::new(RerouteMiddleware(1))).unwrap();
+        let result = graph.run_traced(0, "ticket").await;
+        assert!(matches!(result.error, Some(LLMGraphError::AgentError(_))));
+        assert_eq!(result.steps.len(), 1);
+    }
+
+    /// Test agent returning typed responses depending on its input
+    struct DispatchAgent;
+
+    #[async_trait]
+    impl Agent for DispatchAgent {
+        async fn run(
+            &mut self,
+            _input: &str,
+            _tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ) -> (String, Option<i32>) {
+            ("Triaged".to_string(), Some(1))
+        }
+
+        async fn execute(
+            &mut self,
+            input: &str,
+            _tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+            _ctx: &crate::RunContext,
+        ) -> crate::AgentResponse {
+            use crate::AgentResponse;
+            match input {
+                "" => AgentResponse::fail(crate::errors::AgentError::InvalidInput("empty ticket".to_string())),
+                "lost" => AgentResponse::fork("ticket", vec![1, 9], 3),
+                _ => AgentResponse::fork("ticket", vec![1, 2], 3).with_metadata("confidence", serde_json::json!(0.9)),
+            }
+        }
+
+        fn get_name(&self) -> &str {
+            "Dispatch"
+        }
     }
 
-    /// Test graph execution with simple chain
+    /// Test that the executor acts on typed agent responses
     #[tokio::test]
-    async fn test_simple_chain_execution() {
+    async fn test_agent_responses() {
+        use crate::errors::{AgentError, NodeError};
+        use crate::LLMGraphError;
+
         let mut graph = Graph::new();
-        
-        // Create a simple chain: Agent0 -> Agent1 -> Agent2
-        graph.add_node(0, Box::new(FixedRouteAgent::new("Router", 1)));
-        graph.add_node(1, Box::new(FixedRouteAgent::new("Processor", 2)));
-        graph.add_node(2, Box::new(EchoAgent::new("Final")));
-        
-        // Connect nodes
-        graph.add_edge(0, 1).unwrap();
-        graph.add_edge(1, 2).unwrap();
-        
-        // Execute
-        let result = graph.run(0, "test input").await;
-        
-        assert!(result.contains("Routing"));
-        assert!(result.contains("Echo"));
-        println!("Chain execution result:\n{}", result);
+        graph.add_node(0, Box::new(DispatchAgent));
+        graph.add_node(1, Box::new(EchoAgent::new("Billing")));
+        graph.add_node(2, Box::new(EchoAgent::new("Shipping")));
+        graph.add_node(3, Box::new(EchoAgent::new("Reply")));
+
+        // Forks run the branches with the agent's output, then continue at the join
+        let result = graph.run_traced(0, "refund my order").await;
+        assert!(result.is_success());
+        let names: Vec<&str> = result.steps.iter().map(|step| step.agent_name.as_str()).collect();
+        assert_eq!(names, vec!["Dispatch", "Billing", "Shipping", "Reply"]);
+        assert_eq!(result.steps[0].next_id, Some(3));
+        assert_eq!(result.steps[0].metadata["confidence"], serde_json::json!(0.9));
+        assert!(result.steps[1].metadata.is_empty());
+        assert_eq!(result.output, "Echo: Echo: ticket\nEcho: ticket");
+
+        // Typed failures stop the run with the agent's error
+        let result = graph.run_traced(0, "").await;
+        assert!(matches!(
+            result.error,
+            Some(LLMGraphError::AgentError(AgentError::InvalidInput(_)))
+        ));
+        assert_eq!(result.steps.len(), 1);
+        assert!(result.steps[0].error.is_some());
+
+        // Forks to unknown nodes fail before any branch runs
+        let result = graph.run_traced(0, "lost").await;
+        assert!(matches!(result.error, Some(LLMGraphError::NodeError(NodeError::NodeNotFound(9)))));
+        assert_eq!(result.steps.len(), 1);
+
+        // Agents implementing only `run` are adapted
+        let mut agent = EchoAgent::new("Echo");
+        let ctx = crate::RunContext::new(0, Default::default());
+        let response = agent.execute("hi", &EmptyRegistry, &ctx).await;
+        assert!(matches!(response.action, crate::AgentAction::End));
+        assert_eq!(response.output, "Echo: hi");
     }
 
     // ================================
@@ -250,6 +1456,50 @@ mod tests {
         }
     }
 
+    /// Test agent that returns a typed failure a certain number of times
+    pub struct FailingAgent {
+        failures_remaining: usize,
+    }
+
+    impl FailingAgent {
+        pub fn new(failures: usize) -> Self {
+            Self { failures_remaining: failures }
+        }
+    }
+
+    #[async_trait]
+    impl Agent for FailingAgent {
+        async fn run(
+            &mut self,
+            input: &str,
+            tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ) -> (String, Option<i32>) {
+            let ctx = crate::RunContext::new(0, Default::default());
+            self.execute(input, tool_registry, &ctx).await.into()
+        }
+
+        async fn execute(
+            &mut self,
+            input: &str,
+            _tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+            _ctx: &crate::RunContext,
+        ) -> crate::AgentResponse {
+            if self.failures_remaining > 0 {
+                self.failures_remaining -= 1;
+                crate::AgentResponse::fail(crate::errors::AgentError::ExecutionFailed {
+                    agent: "FailingAgent".to_string(),
+                    error: "temporary failure".to_string(),
+                })
+            } else {
+                crate::AgentResponse::end(format!("Success: {}", input))
+            }
+        }
+
+        fn get_name(&self) -> &str {
+            "FailingAgent"
+        }
+    }
+
     /// Test retry agent with exponential backoff
     #[tokio::test]
     async fn test_retry_agent() {
@@ -266,6 +1516,149 @@ mod tests {
         println!("Retry agent test passed");
     }
 
+    /// Typed agent whose successful output mentions errors
+    struct LintAgent;
+
+    #[async_trait]
+    impl Agent for LintAgent {
+        async fn run(
+            &mut self,
+            input: &str,
+            tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+        ) -> (String, Option<i32>) {
+            let ctx = crate::RunContext::new(0, Default::default());
+            self.execute(input, tool_registry, &ctx).await.into()
+        }
+
+        async fn execute(
+            &mut self,
+            _input: &str,
+            _tool_registry: &(dyn ToolRegistryTrait + Send + Sync),
+            _ctx: &crate::RunContext,
+        ) -> crate::AgentResponse {
+            crate::AgentResponse::end("0 errors found")
+        }
+
+        fn get_name(&self) -> &str {
+            "Lint"
+        }
+    }
+
+    /// Test that typed failures are retried, and reported once retries run out
+    #[tokio::test]
+    async fn test_retry_agent_typed_failures() {
+        let ctx = crate::RunContext::new(0, Default::default());
+
+        let mut retry_agent = RetryAgent::new(Box::new(FailingAgent::new(2)))
+            .with_max_retries(3)
+            .with_strategy(RetryStrategy::Fixed(Duration::from_millis(1)))
+            .with_retry_condition(|_| false);
+        let response = retry_agent.execute("test", &EmptyRegistry, &ctx).await;
+        assert!(!response.is_failure());
+        assert_eq!(response.output, "Success: test");
+
+        let mut retry_agent = RetryAgent::new(Box::new(FailingAgent::new(5)))
+            .with_max_retries(1)
+            .with_strategy(RetryStrategy::Fixed(Duration::from_millis(1)));
+        let response = retry_agent.execute("test", &EmptyRegistry, &ctx).await;
+        assert!(response.is_failure());
+        assert!(response.output.starts_with("All 1 retries exhausted"));
+
+        // Run-only agents that keep failing are reported the same way
+        let mut retry_agent = RetryAgent::new(Box::new(FlakeyAgent::new(5)))
+            .with_max_retries(1)
+            .with_strategy(RetryStrategy::Fixed(Duration::from_millis(1)));
+        let (response, _) = retry_agent.run("test", &EmptyRegistry).await;
+        assert_eq!(response, "All 1 retries exhausted. Last output: Error: temporary failure");
+
+        // Typed agents are only retried on typed failures, not on keywords
+        let mut retry_agent = RetryAgent::new(Box::new(LintAgent))
+            .with_max_retries(2)
+            .with_strategy(RetryStrategy::Fixed(Duration::from_millis(1)));
+        let response = retry_agent.execute("main.rs", &EmptyRegistry, &ctx).await;
+        assert!(!response.is_failure());
+        assert_eq!(response.output, "0 errors found");
+
+        // Parallel agents that time out fail too, at agent level on their own
+        let timing_out = || {
+            let slow = SlowAgent {
+                name: "Slow".to_string(),
+                target_node: None,
+                delay: Duration::from_millis(200),
+            };
+            let parallel = ParallelAgent::new().add_agent(Box::new(slow)).with_timeout(Duration::from_millis(10));
+            RetryAgent::new(Box::new(parallel))
+                .with_max_retries(1)
+                .with_strategy(RetryStrategy::Fixed(Duration::from_millis(1)))
+        };
+        match timing_out().execute("test", &EmptyRegistry, &ctx).await.action {
+            crate::AgentAction::Fail(error) => assert!(matches!(
+                error.root(),
+                crate::LLMGraphError::AgentError(crate::errors::AgentError::ExecutionFailed { .. })
+            )),
+            other => panic!("Expected a timeout, got {:?}", other),
+        }
+
+        // and with the node's ID inside a graph
+        let mut graph = Graph::new();
+        graph.add_node(4, Box::new(timing_out()));
+        let error = graph.run_traced(4, "test").await.error.unwrap();
+        assert!(matches!(
+            error.root(),
+            crate::LLMGraphError::NodeError(crate::errors::NodeError::Timeout { node_id: 4, .. })
+        ));
+    }
+
+    /// Test that errors raised during a run say where they happened
+    #[tokio::test]
+    async fn test_error_context() {
+        use crate::errors::{AgentError, ErrorFrame};
+        use crate::LLMGraphError;
+        use std::sync::Arc;
+
+        let mut inner = Graph::new();
+        inner.add_node(0, Box::new(FixedRouteAgent::new("Plan", 1)));
+        inner.add_node(1, Box::new(
+            RetryAgent::new(Box::new(FailingAgent::new(5)))
+                .with_max_retries(1)
+                .with_strategy(RetryStrategy::Fixed(Duration::from_millis(1))),
+        ));
+        let mut graph = Graph::new();
+        graph.add_node(2, Box::new(SubgraphAgent::new("Writing", Arc::new(inner), 0)));
+
+        let result = graph.run_traced(2, "draft").await;
+        assert!(matches!(result.error, Some(LLMGraphError::AgentError(AgentError::ExecutionFailed { .. }))));
+        let context = result.error_context.as_ref().unwrap();
+        assert_eq!(context.run_id.as_deref(), Some(result.run_id.as_str()));
+        assert_eq!(
+            context.path,
+            vec![
+                ErrorFrame::node(2, "Writing"),
+                ErrorFrame::node(1, "Retry[FailingAgent]"),
+                ErrorFrame::agent("FailingAgent").with_attempt(2),
+            ]
+        );
+        assert_eq!((context.node_id(), context.agent(), context.attempt()), (Some(1), Some("FailingAgent"), Some(2)));
+        assert_eq!(
+            context.to_string(),
+            format!(
+                "run {}: node 2 'Writing' > node 1 'Retry[FailingAgent]' > 'FailingAgent' (attempt 2)",
+                result.run_id
+            )
+        );
+        assert!(graph.run(2, "draft").await.contains("execution failed: temporary failure (at run "));
+
+        // Parallel agents name the child that failed
+        let mut parallel = ParallelAgent::new().add_agent(Box::new(FailingAgent::new(1)));
+        let ctx = crate::RunContext::new(0, Default::default());
+        match parallel.execute("draft", &EmptyRegistry, &ctx).await.action {
+            crate::AgentAction::Fail(error) => {
+                assert_eq!(error.context().unwrap().path, vec![ErrorFrame::agent("FailingAgent")]);
+            }
+            other => panic!("Expected a failure, got {:?}", other),
+        }
+    }
+
     // ================================
     // VALIDATOR AGENT TESTS
     // ================================
@@ -395,6 +1788,24 @@ mod tests {
         assert!(result.is_ok());
         let value = result.unwrap();
         assert_eq!(value["result"], 8.0);
+
+        // Failures are typed
+        use crate::errors::ToolError;
+        use crate::LLMGraphError;
+        assert!(matches!(
+            registry.execute_tool("missing", "{}"),
+            Err(LLMGraphError::ToolError(ToolError::ToolNotFound(_)))
+        ));
+        assert!(matches!(
+            registry.execute_tool("calculator", "not json"),
+            Err(LLMGraphError::ToolError(ToolError::InvalidArguments { .. }))
+        ));
+        let mut failing = crate::ToolRegistry::new();
+        failing.register_tool(lookup_tool(), |_| Err("dictionary offline".to_string()));
+        assert!(matches!(
+            failing.execute_tool("lookup", "{}"),
+            Err(LLMGraphError::ToolError(ToolError::ToolExecutionFailed { .. }))
+        ));
         
         println!("Tool registry test passed");
     }
@@ -422,10 +1833,932 @@ mod tests {
         let api_err = ApiError::RateLimitExceeded;
         let llm_err: LLMGraphError = api_err.into();
         assert!(format!("{}", llm_err).contains("rate limit"));
+
+        // HTTP statuses map to specific errors
+        assert!(matches!(ApiError::from_status(401, ""), ApiError::AuthenticationFailed));
+        assert!(matches!(ApiError::from_status(429, ""), ApiError::RateLimitExceeded));
+        assert!(matches!(ApiError::from_status(504, ""), ApiError::Timeout));
+        assert!(matches!(ApiError::from_status(500, "boom"), ApiError::Status { status: 500, .. }));
+
+        // Errors expose their cause through `source`
+        let llm_err: LLMGraphError = GraphError::Cancelled.into();
+        let source = std::error::Error::source(&llm_err).unwrap();
+        assert_eq!(source.to_string(), "Run was cancelled");
         
         println!("Error handling test passed");
     }
 
+    /// Serve one HTTP response on a local port; returns the URL and a handle
+    /// resolving to the raw request
+    async fn serve_once(status: &str, body: &str) -> (String, tokio::task::JoinHandle<String>) {
+        serve_parts(status, "application/json", vec![body.to_string()]).await
+    }
+
+    /// Serve one response whose body is written in separate `parts`, as a
+    /// streaming server would. The body ends when the connection closes.
+    async fn serve_parts(status: &str, content_type: &str, parts: Vec<String>) -> (String, tokio::task::JoinHandle<String>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        let head = format!(
+            "HTTP/1.1 {}\r\ncontent-type: {}\r\nconnection: close\r\n\r\n",
+            status, content_type
+        );
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = vec![0; 4096];
+            // Read the headers, then as much body as they announce
+            loop {
+                let read = socket.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text
+                        .lines()
+                        .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length {
+                        break;
+                    }
+                }
+                if read == 0 {
+                    break;
+                }
+            }
+            socket.write_all(head.as_bytes()).await.unwrap();
+            for part in parts {
+                socket.write_all(part.as_bytes()).await.unwrap();
+                socket.flush().await.unwrap();
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            socket.shutdown().await.unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+        (url, server)
+    }
+
+    /// Test the OpenAI-compatible client against a local stub server
+    #[tokio::test]
+    async fn test_llm_client() {
+        use crate::errors::ApiError;
+        use crate::{ChatRequest, LLMGraphError, LlmClient, OpenAiCompatibleClient};
+
+        let body = r#"{
+            "model": "stub-model",
+            "choices": [{"message": {"role": "assistant", "content": "Hi there"}, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7}
+        }"#;
+        let (url, server) = serve_once("200 OK", body).await;
+        let base_url = url.trim_end_matches("/chat/completions").to_string();
+        let client = OpenAiCompatibleClient::new(&base_url)
+            .with_api_key("key")
+            .with_model("default-model");
+        assert_eq!(client.chat_url(), url);
+        let endpoint = "https://example.openai.azure.com/openai/deployments/gpt/chat/completions?api-version=2024-06-01";
+        assert_eq!(client.clone().with_endpoint(endpoint).chat_url(), endpoint);
+
+        let message = Message {
+            role: "user".to_string(),
+            content: Some("Hello".to_string()),
+            tool_calls: None,
+        };
+        let response = client.chat(ChatRequest::new(vec![message.clone()])).await.unwrap();
+        assert_eq!(response.content(), Some("Hi there"));
+        assert_eq!(response.model.as_deref(), Some("stub-model"));
+        assert_eq!(response.usage.as_ref().unwrap().total_tokens, 7);
+        assert!(response.tool_calls().is_empty());
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1/chat/completions"));
+        assert!(request.to_lowercase().contains("authorization: bearer key"));
+        assert!(request.contains(r#""model":"default-model""#));
+        assert!(!request.contains("temperature") && !request.contains("tools"), "Unset fields are omitted");
+
+        // Requests override the default model; clients without a key send no auth
+        let (url, server) = serve_once("200 OK", body).await;
+        let client = OpenAiCompatibleClient::new(url);
+        let request = ChatRequest::new(vec![message.clone()]).with_model("other-model").with_temperature(0.5);
+        client.chat(request).await.unwrap();
+        let request = server.await.unwrap();
+        assert!(request.contains(r#""model":"other-model""#) && request.contains(r#""temperature":0.5"#));
+        assert!(!request.to_lowercase().contains("authorization"));
+
+        // A request without any model fails before sending
+        let result = client.chat(ChatRequest::new(vec![message.clone()])).await;
+        assert!(matches!(result, Err(LLMGraphError::ApiError(ApiError::RequestFailed(_)))));
+
+        // Slow servers hit the client's timeout
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = OpenAiCompatibleClient::new(format!("http://{}/v1", listener.local_addr().unwrap()))
+            .with_model("model")
+            .with_timeout(Duration::from_millis(50));
+        let result = client.chat(ChatRequest::new(vec![message])).await;
+        assert!(matches!(result, Err(LLMGraphError::ApiError(ApiError::Timeout))));
+    }
+
+    /// The JSON body of a request captured by a stub server
+    fn request_json(request: &str) -> serde_json::Value {
+        let (_, body) = request.split_once("\r\n\r\n").expect("The request has a body");
+        serde_json::from_str(body).unwrap()
+    }
+
+    /// Test parsing server-sent events split across arbitrary chunks
+    #[test]
+    fn test_sse_parser() {
+        use crate::generate::sse::SseParser;
+
+        let mut parser = SseParser::new();
+        assert!(parser.push(b"data: {\"a\"").is_empty());
+        assert_eq!(parser.push(b": 1}\r\n\r\n: keep-alive\n\nevent: x\ndata: one\ndata: two\n\nda"), vec![
+            "{\"a\": 1}".to_string(),
+            "one\ntwo".to_string(),
+        ]);
+        assert!(parser.push(b"ta:[DONE]").is_empty());
+        assert_eq!(parser.finish(), Some("[DONE]".to_string()));
+        assert_eq!(parser.finish(), None);
+    }
+
+    /// Test streaming a chat completion from a local SSE stub server
+    #[tokio::test]
+    async fn test_chat_stream() {
+        use crate::errors::ApiError;
+        use crate::{ChatEvent, ChatRequest, LLMGraphError, LlmClient, OpenAiCompatibleClient};
+        use futures::StreamExt;
+
+        let events = [
+            r#"{"model":"stub-model","choices":[{"index":0,"delta":{"role":"assistant","content":""}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"content":"Let me "}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"content":"check."}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"lookup","arguments":""}}]}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"key\":"}}]}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"a\"}"}}]}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}]}"#,
+            r#"{"choices":[],"usage":{"prompt_tokens":3,"completion_tokens":4,"total_tokens":7}}"#,
+            "[DONE]",
+        ];
+        let body: String = events.iter().map(|event| format!("data: {}\n\n", event)).collect();
+        // Split the body mid-event to exercise reassembly
+        let parts = body.as_bytes().chunks(37).map(|part| String::from_utf8(part.to_vec()).unwrap()).collect();
+        let (url, server) = serve_parts("200 OK", "text/event-stream", parts).await;
+        let client = OpenAiCompatibleClient::new(url).with_model("model");
+        let message = Message {
+            role: "user".to_string(),
+            content: Some("Look up a".to_string()),
+            tool_calls: None,
+        };
+
+        let mut stream = client.chat_stream(ChatRequest::new(vec![message.clone()])).await.unwrap();
+        let mut deltas = Vec::new();
+        let mut arguments = String::new();
+        let mut done = None;
+        while let Some(event) = stream.next().await {
+            match event.unwrap() {
+                ChatEvent::ContentDelta(delta) => deltas.push(delta),
+                ChatEvent::ToolCallDelta { arguments: fragment, .. } => arguments.push_str(&fragment),
+                ChatEvent::Done(response) => done = Some(response),
+            }
+        }
+        assert_eq!(deltas, vec!["Let me ", "check."]);
+        assert_eq!(arguments, r#"{"key":"a"}"#);
+
+        let response = done.expect("The stream ends with the assembled response");
+        assert_eq!(response.content(), Some("Let me check."));
+        assert_eq!(response.model.as_deref(), Some("stub-model"));
+        assert_eq!(response.usage.as_ref().unwrap().total_tokens, 7);
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("tool_calls"));
+        let calls = response.tool_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!((calls[0].id.as_str(), calls[0].function.name.as_str()), ("call_1", "lookup"));
+        assert_eq!(calls[0].function.arguments, r#"{"key":"a"}"#);
+        assert!(server.await.unwrap().contains(r#""stream":true"#));
+
+        // Errors sent mid-stream end the stream
+        let body = "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\ndata: {\"error\":{\"message\":\"overloaded\"}}\n\n";
+        let (url, _) = serve_parts("200 OK", "text/event-stream", vec![body.to_string()]).await;
+        let client = OpenAiCompatibleClient::new(url).with_model("model");
+        let events: Vec<_> = client.chat_stream(ChatRequest::new(vec![message.clone()])).await.unwrap().collect().await;
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], Ok(ChatEvent::ContentDelta(_))));
+        assert!(matches!(events[1], Err(LLMGraphError::ApiError(ApiError::InvalidResponse(_)))));
+
+        // The timeout applies per chunk, so streams may outlast it
+        let parts = (0..20).map(|_| "data: {\"choices\":[{\"delta\":{\"content\":\".\"}}]}\n\n".to_string()).collect();
+        let (url, _) = serve_parts("200 OK", "text/event-stream", parts).await;
+        let client = OpenAiCompatibleClient::new(url)
+            .with_model("model")
+            .with_timeout(Duration::from_millis(40));
+        let events: Vec<_> = client.chat_stream(ChatRequest::new(vec![message.clone()])).await.unwrap().collect().await;
+        assert_eq!(events.len(), 21);
+        assert!(matches!(events[20], Ok(ChatEvent::Done(_))));
+
+        // But a stream that stalls fails
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0; 4096];
+            let _ = socket.read(&mut buffer).await;
+            let head = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n";
+            socket.write_all(head.as_bytes()).await.unwrap();
+            socket.write_all(b"data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n").await.unwrap();
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        });
+        let client = OpenAiCompatibleClient::new(url)
+            .with_model("model")
+            .with_timeout(Duration::from_millis(100));
+        let events: Vec<_> = client.chat_stream(ChatRequest::new(vec![message.clone()])).await.unwrap().collect().await;
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[1], Err(LLMGraphError::ApiError(ApiError::Timeout))));
+
+        // Unsuccessful statuses fail before streaming
+        let (url, _) = serve_once("401 Unauthorized", "bad key").await;
+        let client = OpenAiCompatibleClient::new(url).with_model("model");
+        let result = client.chat_stream(ChatRequest::new(vec![message])).await;
+        assert!(matches!(result, Err(LLMGraphError::ApiError(ApiError::AuthenticationFailed))));
+    }
+
+    /// Test that clients without native streaming replay their response
+    #[tokio::test]
+    async fn test_default_chat_stream() {
+        use crate::{ChatEvent, ChatRequest, ChatResponse, LLMGraphResult, LlmClient};
+        use futures::StreamExt;
+
+        struct CannedClient;
+
+        #[async_trait]
+        impl LlmClient for CannedClient {
+            async fn chat(&self, _request: ChatRequest) -> LLMGraphResult<ChatResponse> {
+                Ok(ChatResponse::from_text("Hello!"))
+            }
+        }
+
+        let events: Vec<_> = CannedClient.chat_stream(ChatRequest::default()).await.unwrap().collect().await;
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], Ok(ChatEvent::ContentDelta(delta)) if delta == "Hello!"));
+        assert!(matches!(&events[1], Ok(ChatEvent::Done(response)) if response.content() == Some("Hello!")));
+    }
+
+    /// Test translating messages to and from the Anthropic Messages API
+    #[tokio::test]
+    async fn test_anthropic_client() {
+        use crate::errors::ApiError;
+        use crate::models::tools::{FunctionCall, ToolCall};
+        use crate::{AnthropicClient, ChatRequest, LLMGraphError, LlmClient};
+
+        let body = r#"{
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "stub-model",
+            "content": [
+                {"type": "thinking", "thinking": "..."},
+                {"type": "text", "text": "Looking it up."},
+                {"type": "tool_use", "id": "toolu_2", "name": "lookup", "input": {"word": "graph"}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        }"#;
+        let (url, server) = serve_once("200 OK", body).await;
+        let client = AnthropicClient::new("ant-key")
+            .with_base_url(url.trim_end_matches("/v1/chat/completions"))
+            .with_model("default-model")
+            .with_max_tokens(256);
+
+        let message = |role: &str, content: &str| Message {
+            role: role.to_string(),
+            content: Some(content.to_string()),
+            tool_calls: None,
+        };
+        let call = |id: &str| ToolCall {
+            id: id.to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: "lookup".to_string(),
+                arguments: r#"{"word":"a"}"#.to_string(),
+            },
+            thought_signature: None,
+        };
+        let messages = vec![
+            message("system", "Be brief."),
+            message("user", "Look up two words"),
+            Message {
+                role: "assistant".to_string(),
+                content: None,
+                tool_calls: Some(vec![call("toolu_0"), call("toolu_1")]),
+            },
+            message("tool", "first"),
+            message("tool", "second"),
+        ];
+        let request = ChatRequest::new(messages.clone()).with_tools(vec![lookup_tool()]);
+        let response = client.chat(request).await.unwrap();
+
+        assert_eq!(response.content(), Some("Looking it up."));
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(response.model.as_deref(), Some("stub-model"));
+        assert_eq!(response.usage.as_ref().unwrap().total_tokens, 15);
+        let calls = response.tool_calls();
+        assert_eq!((calls[0].id.as_str(), calls[0].function.name.as_str()), ("toolu_2", "lookup"));
+        assert_eq!(calls[0].function.arguments, r#"{"word":"graph"}"#);
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1/messages"));
+        assert!(request.to_lowercase().contains("x-api-key: ant-key"));
+        assert!(request.to_lowercase().contains("anthropic-version: 2023-06-01"));
+        let body = request_json(&request);
+        assert_eq!(body["model"], "default-model");
+        assert_eq!(body["max_tokens"], 256);
+        assert_eq!(body["system"], "Be brief.");
+        assert!(body.get("temperature").is_none());
+        assert_eq!(body["tools"][0]["name"], "lookup");
+        assert_eq!(body["tools"][0]["input_schema"]["type"], "object");
+        assert_eq!(body["messages"], serde_json::json!([
+            {"role": "user", "content": [{"type": "text", "text": "Look up two words"}]},
+            {"role": "assistant", "content": [
+                {"type": "tool_use", "id": "toolu_0", "name": "lookup", "input": {"word": "a"}},
+                {"type": "tool_use", "id": "toolu_1", "name": "lookup", "input": {"word": "a"}}
+            ]},
+            {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_0", "content": "first"},
+                {"type": "tool_result", "tool_use_id": "toolu_1", "content": "second"}
+            ]}
+        ]));
+
+        // Stop reasons map onto OpenAI finish reasons
+        let body = r#"{"content": [{"type": "text", "text": "Cut"}], "stop_reason": "max_tokens"}"#;
+        let (url, _) = serve_once("200 OK", body).await;
+        let client = AnthropicClient::new("ant-key").with_base_url(url.trim_end_matches("/v1/chat/completions"));
+        let request = ChatRequest::new(vec![message("user", "Hi")]).with_model("model");
+        let response = client.chat(request).await.unwrap();
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("length"));
+        assert!(response.tool_calls().is_empty());
+
+        // JSON schema response formats are answered through a forced tool call
+        let body = r#"{
+            "content": [{"type": "tool_use", "id": "toolu_3", "name": "weather", "input": {"city": "Paris"}}],
+            "stop_reason": "tool_use"
+        }"#;
+        let (url, server) = serve_once("200 OK", body).await;
+        let client = AnthropicClient::new("ant-key").with_base_url(url.trim_end_matches("/v1/chat/completions"));
+        let schema = serde_json::json!({"type": "object", "properties": {"city": {"type": "string"}}});
+        let request = ChatRequest::new(vec![message("user", "Weather?")])
+            .with_model("model")
+            .with_response_format(crate::ResponseFormat::json_schema("weather", schema.clone()));
+        let response = client.chat(request).await.unwrap();
+        assert_eq!(response.content(), Some(r#"{"city":"Paris"}"#));
+        assert!(response.tool_calls().is_empty());
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("stop"));
+        let body = request_json(&server.await.unwrap());
+        assert_eq!(body["tools"][0]["name"], "weather");
+        assert_eq!(body["tools"][0]["input_schema"], schema);
+        assert_eq!(body["tool_choice"], serde_json::json!({"type": "tool", "name": "weather"}));
+        assert!(body.get("response_format").is_none());
+
+        // Tool messages must answer a tool call
+        let request = ChatRequest::new(vec![message("user", "Hi"), message("tool", "orphan")]).with_model("model");
+        let result = client.chat(request).await;
+        assert!(matches!(result, Err(LLMGraphError::ApiError(ApiError::RequestFailed(_)))));
+
+        let (url, _) = serve_once("529 Overloaded", r#"{"type": "error"}"#).await;
+        let client = AnthropicClient::new("ant-key").with_base_url(url).with_model("model");
+        let result = client.chat(ChatRequest::new(vec![message("user", "Hi")])).await;
+        assert!(matches!(result, Err(LLMGraphError::ApiError(ApiError::Status { status: 529, .. }))));
+    }
+
+    /// Test the Ollama client's chat and model listing against a stub server
+    #[tokio::test]
+    async fn test_ollama_client() {
+        use crate::models::tools::{FunctionCall, ToolCall};
+        use crate::{ChatRequest, LlmClient, OllamaClient};
+
+        let body = r#"{
+            "model": "llama3.1",
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{"function": {"name": "lookup", "arguments": {"word": "graph"}}}]
+            },
+            "done": true,
+            "done_reason": "stop",
+            "prompt_eval_count": 12,
+            "eval_count": 3
+        }"#;
+        let (url, server) = serve_once("200 OK", body).await;
+        let base_url = url.trim_end_matches("/v1/chat/completions").to_string();
+        let client = OllamaClient::new(&base_url).with_model("llama3.1");
+
+        let messages = vec![
+            Message {
+                role: "user".to_string(),
+                content: Some("Look up graph".to_string()),
+                tool_calls: None,
+            },
+            Message {
+                role: "assistant".to_string(),
+                content: None,
+                tool_calls: Some(vec![ToolCall {
+                    id: "call_0".to_string(),
+                    call_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: "lookup".to_string(),
+                        arguments: r#"{"word":"node"}"#.to_string(),
+                    },
+                    thought_signature: None,
+                }]),
+            },
+            Message {
+                role: "tool".to_string(),
+                content: Some("a vertex".to_string()),
+                tool_calls: None,
+            },
+        ];
+        let mut word_tool = lookup_tool();
+        word_tool.tool_type = "custom".to_string();
+        word_tool.function.parameters.properties.insert("word".to_string(), crate::models::tools::Property {
+            prop_type: "string".to_string(),
+            description: None,
+            items: None,
+        });
+        let request = ChatRequest::new(messages).with_tools(vec![word_tool]).with_temperature(0.2);
+        let response = client.chat(request).await.unwrap();
+        assert_eq!(response.content(), None);
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(response.usage.as_ref().unwrap().total_tokens, 15);
+        let calls = response.tool_calls();
+        assert_eq!((calls[0].id.as_str(), calls[0].function.name.as_str()), ("call_0", "lookup"));
+        assert_eq!(calls[0].function.arguments, r#"{"word":"graph"}"#);
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /api/chat"));
+        assert!(!request.to_lowercase().contains("authorization"), "No key is sent by default");
+        let body = request_json(&request);
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["options"]["temperature"], 0.2);
+        assert_eq!(body["tools"], serde_json::json!([{
+            "type": "function",
+            "function": {
+                "name": "lookup",
+                "description": "Looks up a word",
+                "parameters": {"type": "object", "properties": {"word": {"type": "string"}}, "required": []}
+            }
+        }]));
+        assert_eq!(body["messages"][1]["tool_calls"][0]["function"]["arguments"]["word"], "node");
+        assert_eq!(body["messages"][2]["tool_name"], "lookup");
+
+        // Tool messages must answer a tool call, as for the other providers
+        let orphan = vec![Message {
+            role: "tool".to_string(),
+            content: Some("orphan".to_string()),
+            tool_calls: None,
+        }];
+        let result = client.chat(ChatRequest::new(orphan)).await;
+        assert!(matches!(
+            result,
+            Err(crate::LLMGraphError::ApiError(crate::errors::ApiError::RequestFailed(_)))
+        ));
+
+        let body = r#"{"models": [
+            {"name": "llama3.1:8b", "model": "llama3.1:8b", "size": 4920753328, "digest": "abc", "details": {"family": "llama"}},
+            {"name": "qwen2.5:7b"}
+        ]}"#;
+        let (url, server) = serve_once("200 OK", body).await;
+        let client = OllamaClient::new(url.trim_end_matches("/v1/chat/completions")).with_api_key("proxy-key");
+        let models = client.list_models().await.unwrap();
+        let names: Vec<&str> = models.iter().map(|model| model.name.as_str()).collect();
+        assert_eq!(names, vec!["llama3.1:8b", "qwen2.5:7b"]);
+        assert_eq!(models[0].size, Some(4920753328));
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /api/tags"));
+        assert!(request.to_lowercase().contains("authorization: bearer proxy-key"));
+    }
+
+    /// Test the Gemini client against recorded generateContent responses
+    #[tokio::test]
+    async fn test_gemini_client() {
+        use crate::errors::ApiError;
+        use crate::models::tools::{FunctionCall, Property, ToolCall};
+        use crate::{ChatRequest, GeminiClient, LLMGraphError, LlmClient};
+
+        // Recorded from gemini-2.5-flash, trimmed
+        let function_call_fixture = r#"{
+          "candidates": [
+            {
+              "content": {
+                "parts": [
+                  {
+                    "functionCall": {"name": "get_weather", "args": {"city": "Boston"}},
+                    "thoughtSignature": "CiQB0e2Kb3Ax"
+                  }
+                ],
+                "role": "model"
+              },
+              "finishReason": "STOP",
+              "index": 0
+            }
+          ],
+          "usageMetadata": {"promptTokenCount": 41, "candidatesTokenCount": 6, "totalTokenCount": 47},
+          "modelVersion": "gemini-2.5-flash",
+          "responseId": "kZ3ZaOb5Ea2Cz7IP_5WjwQ4"
+        }"#;
+        let (url, server) = serve_once("200 OK", function_call_fixture).await;
+        let base_url = url.trim_end_matches("/chat/completions").to_string();
+        let client = GeminiClient::new("gem-key").with_base_url(&base_url).with_model("gemini-2.5-flash");
+
+        let mut properties = HashMap::new();
+        properties.insert("city".to_string(), Property {
+            prop_type: "string".to_string(),
+            description: Some("The city".to_string()),
+            items: None,
+        });
+        let weather_tool = Tool {
+            tool_type: "function".to_string(),
+            function: Function {
+                name: "get_weather".to_string(),
+                description: "Gets the weather".to_string(),
+                parameters: Parameters {
+                    param_type: "object".to_string(),
+                    properties,
+                    required: vec!["city".to_string()],
+                },
+            },
+        };
+        let message = |role: &str, content: &str| Message {
+            role: role.to_string(),
+            content: Some(content.to_string()),
+            tool_calls: None,
+        };
+        let request = ChatRequest::new(vec![message("system", "Be brief."), message("user", "Weather in Boston?")])
+            .with_tools(vec![weather_tool, lookup_tool()])
+            .with_temperature(0.0);
+        let response = client.chat(request).await.unwrap();
+        assert_eq!(response.content(), None);
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(response.model.as_deref(), Some("gemini-2.5-flash"));
+        assert_eq!(response.usage.as_ref().unwrap().total_tokens, 47);
+        let calls = response.tool_calls();
+        assert_eq!((calls[0].id.as_str(), calls[0].function.name.as_str()), ("call_0", "get_weather"));
+        assert_eq!(calls[0].function.arguments, r#"{"city":"Boston"}"#);
+        assert_eq!(calls[0].thought_signature.as_deref(), Some("CiQB0e2Kb3Ax"));
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1/models/gemini-2.5-flash:generateContent "));
+        assert!(request.to_lowercase().contains("x-goog-api-key: gem-key"));
+        let body = request_json(&request);
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Be brief.");
+        assert_eq!(body["contents"], serde_json::json!([
+            {"role": "user", "parts": [{"text": "Weather in Boston?"}]}
+        ]));
+        assert_eq!(body["generationConfig"]["temperature"], 0.0);
+        let declarations = &body["tools"][0]["functionDeclarations"];
+        assert_eq!(declarations[0]["parameters"]["properties"]["city"], serde_json::json!({
+            "type": "string",
+            "description": "The city"
+        }));
+        assert!(declarations[1].get("parameters").is_none(), "Schemas without properties are omitted");
+
+        // Tool results go back as function responses, with the key in the query
+        let text_fixture = r#"{
+          "candidates": [
+            {
+              "content": {"parts": [{"text": "It's 18°C and sunny in Boston."}], "role": "model"},
+              "finishReason": "STOP",
+              "index": 0
+            }
+          ],
+          "usageMetadata": {"promptTokenCount": 60, "candidatesTokenCount": 11, "totalTokenCount": 71},
+          "modelVersion": "gemini-2.5-flash"
+        }"#;
+        let (url, server) = serve_once("200 OK", text_fixture).await;
+        let client = GeminiClient::new("gem-key")
+            .with_base_url(url.trim_end_matches("/chat/completions"))
+            .with_key_in_query();
+        let messages = vec![
+            message("user", "Weather in Boston?"),
+            Message {
+                role: "assistant".to_string(),
+                content: None,
+                tool_calls: Some(vec![ToolCall {
+                    id: "call_0".to_string(),
+                    call_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: "get_weather".to_string(),
+                        arguments: r#"{"city":"Boston"}"#.to_string(),
+                    },
+                    thought_signature: Some("CiQB0e2Kb3Ax".to_string()),
+                }]),
+            },
+            message("tool", r#""18°C and sunny""#),
+        ];
+        let response = client
+            .chat(ChatRequest::new(messages).with_model("models/gemini-2.5-flash"))
+            .await
+            .unwrap();
+        assert_eq!(response.content(), Some("It's 18°C and sunny in Boston."));
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("stop"));
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1/models/gemini-2.5-flash:generateContent?key=gem-key "));
+        assert!(!request.to_lowercase().contains("x-goog-api-key"));
+        let body = request_json(&request);
+        assert_eq!(body["contents"][1], serde_json::json!({
+            "role": "model",
+            "parts": [{
+                "functionCall": {"id": "call_0", "name": "get_weather", "args": {"city": "Boston"}},
+                "thoughtSignature": "CiQB0e2Kb3Ax"
+            }]
+        }));
+        assert_eq!(body["contents"][2], serde_json::json!({
+            "role": "user",
+            "parts": [{"functionResponse": {"id": "call_0", "name": "get_weather", "response": {"result": "18°C and sunny"}}}]
+        }));
+
+        // Blocked prompts have no candidates
+        let blocked_fixture = r#"{
+          "promptFeedback": {"blockReason": "SAFETY"},
+          "usageMetadata": {"promptTokenCount": 8, "totalTokenCount": 8},
+          "modelVersion": "gemini-2.5-flash"
+        }"#;
+        let (url, _) = serve_once("200 OK", blocked_fixture).await;
+        let client = GeminiClient::new("gem-key")
+            .with_base_url(url.trim_end_matches("/chat/completions"))
+            .with_model("gemini-2.5-flash");
+        let result = client.chat(ChatRequest::new(vec![message("user", "...")])).await;
+        assert!(matches!(result, Err(LLMGraphError::ApiError(ApiError::InvalidResponse(_)))));
+
+        // Keys sent in the query don't leak into errors
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable = format!("http://{}/v1", listener.local_addr().unwrap());
+        drop(listener);
+        let client = GeminiClient::new("secret-key")
+            .with_base_url(unreachable)
+            .with_key_in_query()
+            .with_model("gemini-2.5-flash");
+        let error = client.chat(ChatRequest::new(vec![message("user", "Hi")])).await.unwrap_err();
+        assert!(matches!(error, LLMGraphError::ApiError(ApiError::Http(_))));
+        assert!(!format!("{} {:?}", error, error).contains("secret-key"));
+
+        let (url, _) = serve_once("200 OK", "not json").await;
+        let client = GeminiClient::new("secret-key")
+            .with_base_url(url.trim_end_matches("/chat/completions"))
+            .with_key_in_query()
+            .with_model("gemini-2.5-flash");
+        let error = client.chat(ChatRequest::new(vec![message("user", "Hi")])).await.unwrap_err();
+        assert!(!format!("{} {:?}", error, error).contains("secret-key"));
+    }
+
+    /// Test that sampling and tool choice parameters reach each provider
+    #[tokio::test]
+    async fn test_chat_request_parameters() {
+        use crate::{AnthropicClient, ChatRequest, GeminiClient, LlmClient, OllamaClient, OpenAiCompatibleClient, ToolChoice};
+        use serde_json::json;
+
+        let message = Message {
+            role: "user".to_string(),
+            content: Some("Hi".to_string()),
+            tool_calls: None,
+        };
+        let request = ChatRequest::new(vec![message.clone()])
+            .with_model("model")
+            .with_tools(vec![lookup_tool()])
+            .with_max_tokens(64)
+            .with_top_p(0.5)
+            .with_stop(["END"])
+            .with_seed(7)
+            .with_presence_penalty(0.25)
+            .with_frequency_penalty(0.5)
+            .with_tool_choice(ToolChoice::Function("lookup".to_string()))
+            .with_parallel_tool_calls(false)
+            .with_user("user-1")
+            .with_extra("provider", json!({"order": ["openai"]}));
+
+        let (url, server) = serve_once("200 OK", r#"{"choices": []}"#).await;
+        OpenAiCompatibleClient::new(url).chat(request.clone()).await.unwrap();
+        let body = request_json(&server.await.unwrap());
+        assert_eq!(body["max_tokens"], 64);
+        assert_eq!(body["top_p"], 0.5);
+        assert_eq!(body["stop"], json!(["END"]));
+        assert_eq!(body["seed"], 7);
+        assert_eq!(body["presence_penalty"], 0.25);
+        assert_eq!(body["frequency_penalty"], 0.5);
+        assert_eq!(body["tool_choice"], json!({"type": "function", "function": {"name": "lookup"}}));
+        assert_eq!(body["parallel_tool_calls"], false);
+        assert_eq!(body["user"], "user-1");
+        assert_eq!(body["provider"], json!({"order": ["openai"]}));
+
+        // Extra fields replace typed fields of the same name
+        let (url, server) = serve_once("200 OK", r#"{"choices": []}"#).await;
+        let overridden = request.clone().with_extra("max_tokens", json!(128));
+        OpenAiCompatibleClient::new(url).chat(overridden).await.unwrap();
+        let sent = server.await.unwrap();
+        assert_eq!(sent.matches(r#""max_tokens""#).count(), 1);
+        assert_eq!(request_json(&sent)["max_tokens"], 128);
+
+        let choices = [ToolChoice::Auto, ToolChoice::None, ToolChoice::Required];
+        let serialized: Vec<_> = choices.iter().map(|choice| serde_json::to_value(choice).unwrap()).collect();
+        assert_eq!(serialized, vec![json!("auto"), json!("none"), json!("required")]);
+
+        let (url, server) = serve_once("200 OK", r#"{"content": [], "stop_reason": "end_turn"}"#).await;
+        AnthropicClient::new("key").with_base_url(url).chat(request.clone()).await.unwrap();
+        let body = request_json(&server.await.unwrap());
+        assert_eq!(body["max_tokens"], 64);
+        assert_eq!(body["stop_sequences"], json!(["END"]));
+        assert_eq!(body["tool_choice"], json!({"type": "tool", "name": "lookup", "disable_parallel_tool_use": true}));
+        assert_eq!(body["metadata"], json!({"user_id": "user-1"}));
+        assert!(body.get("seed").is_none() && body.get("stop").is_none());
+
+        let (url, server) = serve_once("200 OK", r#"{"message": {"role": "assistant", "content": "Hi"}}"#).await;
+        OllamaClient::new(url.trim_end_matches("/v1/chat/completions")).chat(request.clone()).await.unwrap();
+        let body = request_json(&server.await.unwrap());
+        assert_eq!(body["options"], json!({
+            "num_predict": 64,
+            "top_p": 0.5,
+            "stop": ["END"],
+            "seed": 7,
+            "presence_penalty": 0.25,
+            "frequency_penalty": 0.5
+        }));
+
+        let (url, server) = serve_once("200 OK", r#"{"candidates": []}"#).await;
+        GeminiClient::new("key").with_base_url(url).chat(request).await.unwrap();
+        let body = request_json(&server.await.unwrap());
+        assert_eq!(body["generationConfig"], json!({
+            "maxOutputTokens": 64,
+            "topP": 0.5,
+            "stopSequences": ["END"],
+            "seed": 7,
+            "presencePenalty": 0.25,
+            "frequencyPenalty": 0.5
+        }));
+        assert_eq!(body["toolConfig"], json!({
+            "functionCallingConfig": {"mode": "ANY", "allowedFunctionNames": ["lookup"]}
+        }));
+
+        // Requests without parameters send none
+        let (url, server) = serve_once("200 OK", r#"{"message": {"role": "assistant", "content": "Hi"}}"#).await;
+        let client = OllamaClient::new(url.trim_end_matches("/v1/chat/completions"));
+        client.chat(ChatRequest::new(vec![message]).with_model("model")).await.unwrap();
+        let body = request_json(&server.await.unwrap());
+        let mut keys: Vec<_> = body.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["messages", "model", "stream"]);
+    }
+
+    /// Test decoding structured responses, re-asking after parse errors
+    #[tokio::test]
+    async fn test_generate_structured() {
+        use crate::errors::ApiError;
+        use crate::generate::generate::generate_structured;
+        use crate::{
+            ChatRequest, ChatResponse, GeminiClient, LLMGraphError, LLMGraphResult, LlmClient, OllamaClient,
+            OpenAiCompatibleClient, ResponseFormat,
+        };
+        use serde::Deserialize;
+        use serde_json::json;
+        use std::sync::Mutex;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Weather {
+            city: String,
+            celsius: f64,
+        }
+
+        /// Answers with scripted replies and records the requests
+        struct ScriptedClient {
+            replies: Mutex<Vec<&'static str>>,
+            requests: Mutex<Vec<ChatRequest>>,
+        }
+
+        #[async_trait]
+        impl LlmClient for ScriptedClient {
+            async fn chat(&self, request: ChatRequest) -> LLMGraphResult<ChatResponse> {
+                self.requests.lock().unwrap().push(request);
+                Ok(ChatResponse::from_text(self.replies.lock().unwrap().remove(0)))
+            }
+        }
+
+        let schema = json!({
+            "type": "object",
+            "properties": {"city": {"type": "string"}, "celsius": {"type": "number"}},
+            "required": ["city", "celsius"]
+        });
+        let message = Message {
+            role: "user".to_string(),
+            content: Some("Weather in Boston?".to_string()),
+            tool_calls: None,
+        };
+
+        let client = ScriptedClient {
+            replies: Mutex::new(vec![r#"{"city": "Boston"}"#, "```json\n{\"city\": \"Boston\", \"celsius\": 18.5}\n```"]),
+            requests: Mutex::new(Vec::new()),
+        };
+        let request = ChatRequest::new(vec![message.clone()]);
+        let weather: Weather = generate_structured(&client, request.clone(), "weather", schema.clone(), 2).await.unwrap();
+        assert_eq!(weather, Weather { city: "Boston".to_string(), celsius: 18.5 });
+
+        let requests = std::mem::take(&mut *client.requests.lock().unwrap());
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].response_format, Some(ResponseFormat::json_schema("weather", schema.clone())));
+        let retry = &requests[1].messages;
+        assert_eq!(retry.len(), 3);
+        assert_eq!(retry[1].content.as_deref(), Some(r#"{"city": "Boston"}"#));
+        assert!(retry[2].content.as_ref().unwrap().contains("missing field `celsius`"));
+
+        // Giving up after the retries
+        let client = ScriptedClient {
+            replies: Mutex::new(vec!["sunny", "still sunny"]),
+            requests: Mutex::new(Vec::new()),
+        };
+        let result = generate_structured::<Weather>(&client, request, "weather", schema.clone(), 1).await;
+        match result {
+            Err(LLMGraphError::ApiError(ApiError::InvalidResponse(message))) => assert!(message.contains("after 2 attempts")),
+            other => panic!("Expected an invalid response error, got {:?}", other),
+        }
+
+        // The OpenAI-compatible client sends the schema as response_format
+        let body = r#"{"choices": [{"message": {"role": "assistant", "content": "{\"city\": \"Boston\", \"celsius\": 18}"}}]}"#;
+        let (url, server) = serve_once("200 OK", body).await;
+        let client = OpenAiCompatibleClient::new(url).with_model("model");
+        let weather: Weather = generate_structured(&client, ChatRequest::new(vec![message]), "weather", schema.clone(), 0)
+            .await
+            .unwrap();
+        assert_eq!(weather.celsius, 18.0);
+        let body = request_json(&server.await.unwrap());
+        assert_eq!(body["response_format"], json!({
+            "type": "json_schema",
+            "json_schema": {"name": "weather", "schema": schema, "strict": true}
+        }));
+
+        // Ollama and Gemini take the schema in their own fields
+        let request = ChatRequest::new(Vec::new())
+            .with_model("model")
+            .with_response_format(ResponseFormat::json_schema("weather", schema.clone()));
+        let (url, server) = serve_once("200 OK", r#"{"message": {"role": "assistant", "content": "{}"}}"#).await;
+        OllamaClient::new(url.trim_end_matches("/v1/chat/completions")).chat(request.clone()).await.unwrap();
+        assert_eq!(request_json(&server.await.unwrap())["format"], schema);
+
+        let (url, server) = serve_once("200 OK", r#"{"candidates": []}"#).await;
+        GeminiClient::new("key").with_base_url(url).chat(request).await.unwrap();
+        let body = request_json(&server.await.unwrap());
+        assert_eq!(body["generationConfig"]["responseMimeType"], "application/json");
+        assert_eq!(body["generationConfig"]["responseJsonSchema"], schema);
+    }
+
+    /// Test that API failures surface as typed errors
+    #[tokio::test]
+    async fn test_api_errors() {
+        use crate::errors::ApiError;
+        use crate::generate::generate::{generate, generate_full_response};
+        use crate::LLMGraphError;
+
+        let (url, _) = serve_once("429 Too Many Requests", r#"{"error": "slow down"}"#).await;
+        let result = generate(url, "key".to_string(), "model".to_string(), 0.0, Vec::new()).await;
+        assert!(matches!(result, Err(LLMGraphError::ApiError(ApiError::RateLimitExceeded))));
+
+        let (url, _) = serve_once("500 Internal Server Error", "overloaded").await;
+        let result = generate_full_response(url, "key".to_string(), "model".to_string(), 0.0, Vec::new(), None).await;
+        match result {
+            Err(LLMGraphError::ApiError(ApiError::Status { status, body })) => {
+                assert_eq!(status, 500);
+                assert_eq!(body, "overloaded");
+            }
+            other => panic!("Expected a status error, got {:?}", other),
+        }
+
+        // Status errors raised by reqwest itself have no body to report
+        let (url, _) = serve_once("500 Internal Server Error", "overloaded").await;
+        let status_error = reqwest::get(url).await.unwrap().error_for_status().unwrap_err();
+        assert!(matches!(ApiError::from(status_error), ApiError::Http(e) if e.status().map(|s| s.as_u16()) == Some(500)));
+
+        let (url, server) = serve_once("200 OK", r#"{"choices": [{"message": {"role": "assistant", "content": "Hi"}}]}"#).await;
+        let result = generate(url, "key".to_string(), "model".to_string(), 0.0, Vec::new()).await;
+        assert_eq!(result.unwrap(), "Hi");
+        assert!(server.await.unwrap().contains("Bearer key"));
+
+        // The URL is used as given, including its query string
+        let (url, server) = serve_once("200 OK", r#"{"choices": [{"message": {"role": "assistant", "content": "Hi"}}]}"#).await;
+        let url = format!("{}?api-version=2024-06-01", url);
+        let result = generate(url, "key".to_string(), "model".to_string(), 0.0, Vec::new()).await;
+        assert_eq!(result.unwrap(), "Hi");
+        assert!(server.await.unwrap().starts_with("POST /v1/chat/completions?api-version=2024-06-01 HTTP/1.1"));
+
+        let (url, _) = serve_once("200 OK", "not json").await;
+        let result = generate(url, "key".to_string(), "model".to_string(), 0.0, Vec::new()).await;
+        assert!(matches!(result, Err(LLMGraphError::ApiError(ApiError::InvalidResponse(_)))));
+
+        // Transport failures keep the reqwest error as their source
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+        let error = generate(url, "key".to_string(), "model".to_string(), 0.0, Vec::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, LLMGraphError::ApiError(ApiError::Http(_))));
+        let api_error = std::error::Error::source(&error).unwrap();
+        assert!(std::error::Error::source(api_error).is_some());
+    }
+
     // ================================
     // INTEGRATION TEST WITH LLM
     // ================================
@@ -474,8 +2807,8 @@ mod tests {
             Vec::new()
         }
         
-        fn execute_tool(&self, _name: &str, _arguments: &str) -> Result<serde_json::Value, String> {
-            Err("No tools available".to_string())
+        fn execute_tool(&self, name: &str, _arguments: &str) -> crate::LLMGraphResult<serde_json::Value> {
+            Err(crate::errors::ToolError::ToolNotFound(name.to_string()).into())
         }
     }
 }
\ No newline at end of file